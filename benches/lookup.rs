@@ -0,0 +1,67 @@
+//! Lookup benchmark over a synthetic feature database of realistic size.
+//!
+//! Run with `cargo bench --bench=lookup` and compare against `cargo bench --bench=lookup
+//! --features=rayon` to see the effect of parallel scoring.
+
+use std::{env, hint::black_box, time::Instant};
+
+use alfred_caniuse_rs::Db;
+use serde_json::json;
+
+const FEATURE_COUNT: usize = 2_000;
+const ITERATIONS: u32 = 200;
+const QUERIES: &[&str] = &["async", "const generic", "str", "try_blocks", "option::zip"];
+
+const WORDS: &[&str] = &[
+    "async", "await", "const", "generics", "impl", "trait", "let", "else", "option", "zip", "str",
+    "slice", "iter", "try", "blocks", "closure", "pattern", "match", "array", "from_fn",
+];
+
+fn main() {
+    // `cargo bench` passes `--bench`; skipped when run as a test by `cargo test --all-targets`
+    if !env::args().any(|arg| arg == "--bench") {
+        return;
+    }
+
+    let db = synthetic_db();
+
+    // first lookup builds search index, which is skipped when it is restored from cache
+    let start = Instant::now();
+    black_box(db.lookup("async", 20));
+    println!("first lookup, building search index: {:?}", start.elapsed());
+
+    for query in QUERIES {
+        let start = Instant::now();
+
+        for _ in 0..ITERATIONS {
+            black_box(db.lookup(black_box(query), 20));
+        }
+
+        let per_iter = start.elapsed() / ITERATIONS;
+        println!("lookup {query:?}: {per_iter:?}/iter");
+    }
+}
+
+/// Builds database with features shaped like those in caniuse.rs's `features.json`.
+fn synthetic_db() -> Db {
+    let word = |idx: usize| WORDS[idx % WORDS.len()];
+
+    let features = (0..FEATURE_COUNT)
+        .map(|idx| {
+            let slug = format!("{}_{}_{idx}", word(idx), word(idx / 3));
+
+            let feature = json!({
+                "title": format!("{} {} {}", word(idx), word(idx / 7), word(idx / 11)),
+                "flag": (idx % 3 == 0).then(|| slug.clone()),
+                "items": [format!("{}::{}", word(idx / 5), word(idx))],
+                "aliases": [format!("{} {}", word(idx / 13), word(idx))],
+                "version": (idx % 4 != 0).then(|| format!("1.{}", idx % 80)),
+                "slug": slug,
+            });
+
+            (slug, feature)
+        })
+        .collect::<serde_json::Map<_, _>>();
+
+    serde_json::from_value(json!({ "versions": {}, "features": features })).unwrap()
+}
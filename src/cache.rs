@@ -1,100 +1,227 @@
 use std::{
-    fs,
-    io::{self, Write as _},
-    path::PathBuf,
-    time::Duration,
+    collections::hash_map::RandomState,
+    env, fs,
+    hash::{BuildHasher as _, Hasher as _},
+    io::{self, Read as _, Write as _},
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, SystemTime},
 };
 
 use eyre::eyre;
 
-use crate::Db;
+use crate::{search::SearchEntry, update::UPDATE_CHECK_FILENAME, Db};
 
 const FOUR_HOURS_SECS: u64 = 3600 * 4;
-const MAX_AGE: Duration = Duration::from_secs(FOUR_HOURS_SECS);
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// Leading byte of cache file identifying its encoding.
+///
+/// Must be bumped whenever the encoding or the layout of [`Db`] changes so that old cache files are
+/// discarded instead of mis-parsed.
+const CACHE_FORMAT_VERSION: u8 = 3;
+
+/// State of the on-disk feature database cache.
+#[derive(Debug)]
+pub enum Cached {
+    /// Cached database is within its max age and can be used as-is.
+    Fresh(Db),
+
+    /// Cached database is older than its max age and should be revalidated.
+    ///
+    /// It can still be used as a fallback if revalidation fails.
+    Expired(Db),
+
+    /// No usable cache exists.
+    Missing,
+}
 
 /// Tries to load and parse DB from disk.
 ///
-/// Most errors will be caught, transformed into `None`s and then the cache path will be cleaned up,
-/// returning None. The caller is then free to fetch from the web and attempt to cache again.
-pub fn cache_fetch() -> Option<Db> {
-    match cache_fetch_inner() {
-        Ok(cached_db) => cached_db,
+/// Most errors will be caught, transformed into `Missing` and then the cache path will be cleaned
+/// up. The caller is then free to fetch from the web and attempt to cache again.
+pub fn cache_fetch() -> Cached {
+    let path = cache_path();
+
+    match cache_fetch_inner(&path) {
+        Ok(cached) => cached,
 
         // if any error occurs regarding file access or decoding
         // we try to delete the file to reset state for next time
         Err(err) => {
-            eprintln!("cache fetch error: {err}");
+            log::warn!("cache fetch error: {err}");
 
             // attempt clean up
             // errors on this are unlikely and are therefore ignored
-            if let Err(err) = fs::remove_file(cache_path()) {
-                eprintln!("failed to clean up cache file: {err}");
+            if let Err(err) = fs::remove_file(&path) {
+                log::warn!("failed to clean up cache file: {err}");
             }
 
-            None
+            Cached::Missing
         }
     }
 }
 
-fn cache_fetch_inner() -> eyre::Result<Option<Db>> {
-    let file = fs::File::open(cache_path());
+fn cache_fetch_inner(path: &Path) -> eyre::Result<Cached> {
+    let file = fs::File::open(path);
 
-    let file = match file {
+    let mut file = match file {
         Ok(file) => file,
 
         // special case for file not found; cache state is clean
-        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Cached::Missing),
 
         // other errors should be reported so clean up can happen
         Err(err) => return Err(err.into()),
     };
 
     // check metadata for when file was updated
-    let cache_modified = file.metadata()?.created()?;
-    let cache_age = cache_modified.elapsed()?;
+    // modified time is used since creation time is not recorded on many Linux file systems
+    let cache_modified = file.metadata()?.modified()?;
+
+    // timestamps in the future, e.g. after a clock correction, are treated as just written
+    let cache_age = cache_modified.elapsed().unwrap_or(Duration::ZERO);
 
-    // and signal caller to delete cache file
-    if cache_age > MAX_AGE {
-        return Err(eyre!("cache is too old"));
+    // caches written in other formats are reported so clean up can happen
+    let mut format_version = [0; 1];
+    file.read_exact(&mut format_version)?;
+
+    if format_version[0] != CACHE_FORMAT_VERSION {
+        return Err(eyre!(
+            "unsupported cache format version: {}",
+            format_version[0]
+        ));
     }
 
     let buf = zstd::decode_all(file)?;
-    let json = serde_json::from_slice(&buf)?;
+    let (mut db, search_index): (Db, Vec<SearchEntry>) = bincode::deserialize(&buf)?;
+
+    // restoring prebuilt index saves rebuilding it on each launch
+    db.set_search_index(search_index);
+
+    // expired caches are kept so they can be revalidated against the remote
+    if cache_age > jittered(max_age()) {
+        return Ok(Cached::Expired(db));
+    }
+
+    Ok(Cached::Fresh(db))
+}
+
+/// Marks cached feature database as fresh without rewriting it.
+///
+/// Used when the remote database is known to be unchanged. Errors are ignored.
+pub fn cache_touch() {
+    let res = fs::File::options()
+        .write(true)
+        .open(cache_path())
+        .and_then(|file| file.set_modified(SystemTime::now()));
 
-    Ok(Some(json))
+    if let Err(err) = res {
+        log::warn!("cache touch error: {err}");
+    }
+}
+
+/// Returns maximum age of cache file, read from `CANIUSE_CACHE_TTL_SECS` if set.
+///
+/// Defaults to four hours when unset or invalid.
+fn max_age() -> Duration {
+    let secs = env::var("CANIUSE_CACHE_TTL_SECS")
+        .ok()
+        .and_then(|secs| secs.trim().parse().ok())
+        .unwrap_or(FOUR_HOURS_SECS);
+
+    Duration::from_secs(secs)
+}
+
+/// Randomly scales duration by up to 10% either way.
+///
+/// Spreads out refreshes of invocations that would otherwise all expire the cache at once.
+fn jittered(duration: Duration) -> Duration {
+    // std seeds hashers from the system RNG, which avoids pulling in a dependency for this
+    let random = RandomState::new().build_hasher().finish();
+    let unit = random as f64 / u64::MAX as f64;
+
+    duration.mul_f64(0.9 + 0.2 * unit)
 }
 
 /// Attempt to cache feature database on disk.
 ///
-/// Errors are ignored and a clean up is attempted.
+/// Errors are ignored. Since the cache file is replaced atomically, a failed write leaves any
+/// existing cache file intact.
 pub fn cache_put(db: &Db) {
-    // if any error occurs writing file access or encoding
-    // we try to delete the file to reset state for next time
-    if let Err(err) = cache_put_inner(db) {
-        eprintln!("cache fetch error: {err}");
-
-        // attempt clean up
-        // errors on this are unlikely and are therefore ignored
-        if let Err(err) = fs::remove_file(cache_path()) {
-            eprintln!("failed to clean up cache file: {err}");
-        }
+    if let Err(err) = cache_put_inner(db, &cache_path()) {
+        log::warn!("cache put error: {err}");
     }
 }
 
-fn cache_put_inner(db: &Db) -> eyre::Result<()> {
+fn cache_put_inner(db: &Db, path: &Path) -> eyre::Result<()> {
     // ensure containing direction of cache file exists
-    fs::create_dir_all(cache_dir())?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut enc = vec![CACHE_FORMAT_VERSION];
+
+    // search index is built now, if not already, so that later launches can skip building it
+    let bin = bincode::serialize(&(db, db.search_index()))?;
+    zstd::stream::copy_encode(&bin[..], &mut enc, zstd_level())?;
+
+    // replacing the file resets the modified datetime, which the caching strategy relies on
+    write_atomic(path, &enc)?;
 
-    // we need to reset the created datetime since the caching strategy relies on it
-    // error is ignored to allow cache creation in first instant
-    let _ = fs::remove_file(cache_path());
+    Ok(())
+}
+
+/// Returns zstd compression level for cache file, read from `CANIUSE_ZSTD_LEVEL` if set.
+///
+/// Higher levels produce smaller files but take longer to write, which delays results on every
+/// cache miss. Since the cache is small, a fast level is used by default. Levels are clamped to the
+/// range supported by zstd.
+fn zstd_level() -> i32 {
+    let level = env::var("CANIUSE_ZSTD_LEVEL")
+        .ok()
+        .and_then(|level| level.trim().parse().ok())
+        .unwrap_or(DEFAULT_ZSTD_LEVEL);
+
+    let range = zstd::compression_level_range();
+    level.clamp(*range.start(), *range.end())
+}
+
+/// Writes file contents by writing to a temporary sibling file and renaming it into place.
+///
+/// Renaming is atomic on the same file system so readers never observe a partially written file
+/// if the process is killed mid-write.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    // Alfred runs a process per keystroke so concurrent writers each need their own temp file
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp_path = path.with_file_name(format!("{file_name}.{}.tmp", process::id()));
 
-    // if create is successful, any existing file is truncated
-    let mut file = fs::File::create(cache_path())?;
+    let res = fs::File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&tmp_path, path));
 
-    let json = serde_json::to_vec_pretty(db)?;
-    let enc = zstd::encode_all(&json[..], zstd::DEFAULT_COMPRESSION_LEVEL)?;
-    file.write_all(&enc)?;
+    if res.is_err() {
+        // errors on this are unlikely and are therefore ignored
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    res
+}
+
+/// Removes cached feature database and update check files.
+///
+/// Files that do not exist are ignored.
+pub fn cache_clear() -> eyre::Result<()> {
+    for path in [cache_path(), cache_dir().join(UPDATE_CHECK_FILENAME)] {
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(eyre!("failed to remove {}: {err}", path.display())),
+        }
+    }
 
     Ok(())
 }
@@ -105,10 +232,62 @@ fn cache_path() -> PathBuf {
 }
 
 /// Returns absolute path to location of cache directory.
-pub(crate) fn cache_dir() -> PathBuf {
+///
+/// Falls back to the system temporary directory when the user cache directory can not be
+/// determined, e.g. when `HOME` is not set.
+pub fn cache_dir() -> PathBuf {
     use dirs::cache_dir as macos_cache_dir;
 
     macos_cache_dir()
-        .unwrap()
+        .unwrap_or_else(env::temp_dir)
         .join("dev.robjtede.alfred-caniuse-rs")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns path of a cache file unique to this test process and test name.
+    fn test_cache_path(name: &str) -> PathBuf {
+        env::temp_dir()
+            .join(format!("alfred-caniuse-rs-test-{}", process::id()))
+            .join(format!("{name}.bin"))
+    }
+
+    fn test_db() -> Db {
+        Db::from_json_str(r#"{ "features": { "let_else": { "title": "let else" } } }"#).unwrap()
+    }
+
+    #[test]
+    fn freshly_written_cache_is_fresh() {
+        let path = test_cache_path("fresh");
+        cache_put_inner(&test_db(), &path).unwrap();
+
+        let cached = cache_fetch_inner(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let Cached::Fresh(db) = cached else {
+            panic!("expected fresh cache, got {cached:?}");
+        };
+        assert_eq!(db.lookup("let else", 20)[0].slug, "let_else");
+    }
+
+    #[test]
+    fn cache_modified_in_future_is_fresh() {
+        let path = test_cache_path("future");
+        cache_put_inner(&test_db(), &path).unwrap();
+
+        // eg. file written before the system clock was corrected backwards
+        let in_an_hour = SystemTime::now() + Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(in_an_hour))
+            .unwrap();
+
+        let cached = cache_fetch_inner(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(cached, Cached::Fresh(_)), "got {cached:?}");
+    }
+}
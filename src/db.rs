@@ -1,52 +1,309 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Ordering, collections::HashMap, env, error::Error as _, io, sync::OnceLock, thread,
+    time::Duration,
+};
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::models::{CompilerVersionData, FeatureData};
+use crate::{
+    error::FetchError,
+    http,
+    models::{parse_version, Channel, CompilerVersionData, FeatureData},
+    search::{self, MatchKind, SearchEntry},
+};
 
 const UA_NAME: &str = env!("CARGO_PKG_NAME");
 const UA_VERSION: &str = env!("CARGO_PKG_VERSION");
+const DEFAULT_FETCH_RETRIES: u32 = 3;
+const MAX_FETCH_RETRIES: u32 = 5;
+const DEFAULT_HTTP_TIMEOUT_SECS: u64 = 10;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(200);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Largest feature database response body that will be read, well above its real size.
+const MAX_BODY_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Restricts lookup results by stabilization status.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StabilityFilter {
+    /// All features are considered.
+    #[default]
+    Any,
+
+    /// Only features that have been stabilized are considered.
+    Stable,
+
+    /// Only features that have not been stabilized are considered.
+    Unstable,
+}
+
+impl StabilityFilter {
+    fn matches(self, feature: &FeatureData) -> bool {
+        match self {
+            StabilityFilter::Any => true,
+            StabilityFilter::Stable => feature.version_number.is_some(),
+            StabilityFilter::Unstable => feature.version_number.is_none(),
+        }
+    }
+}
 
 /// The caniuse features
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Db {
     #[serde(default)]
     base_url: String,
+    #[serde(default)]
     versions: HashMap<String, CompilerVersionData>,
+    #[serde(default)]
     features: HashMap<String, FeatureData>,
+
+    /// `ETag` header of the response this database was fetched from.
+    #[serde(default)]
+    etag: Option<String>,
+
+    /// `Last-Modified` header of the response this database was fetched from.
+    #[serde(default)]
+    last_modified: Option<String>,
+
+    /// Normalized search fields, built lazily on first lookup.
+    #[serde(skip)]
+    search_index: OnceLock<Vec<SearchEntry>>,
 }
 
 impl Db {
     /// Fetch the database from the given URL.
-    pub fn fetch(url: &str) -> eyre::Result<Db> {
-        let mut db = ureq::get(&format!("{url}/features.json"))
-            .set("user-agent", &format!("{UA_NAME}/{UA_VERSION}"))
-            .call()?
-            .into_json::<Db>()?;
+    pub fn fetch(url: &str) -> Result<Db, FetchError> {
+        Self::fetch_with_agent(&http_agent(url), url)
+    }
+
+    /// Fetch the database from the given URL using the given HTTP agent.
+    ///
+    /// Unlike [`fetch`](Self::fetch), timeouts and proxies are left to the agent's configuration.
+    pub fn fetch_with_agent(agent: &ureq::Agent, url: &str) -> Result<Db, FetchError> {
+        let res = call_with_retry(url, || features_request(agent, url))?;
+        Self::from_response(url, res)
+    }
+
+    /// Fetch the database from the given URL unless it is unchanged since `cached` was fetched.
+    ///
+    /// Returns `None` if the server indicates that `cached` is still up-to-date.
+    pub fn fetch_if_modified(url: &str, cached: &Db) -> Result<Option<Db>, FetchError> {
+        let agent = http_agent(url);
+
+        let res = call_with_retry(url, || {
+            let mut req = features_request(&agent, url);
+
+            if let Some(etag) = cached.etag.as_deref() {
+                req = req.set("if-none-match", etag);
+            }
+
+            if let Some(last_modified) = cached.last_modified.as_deref() {
+                req = req.set("if-modified-since", last_modified);
+            }
+
+            req
+        })?;
+
+        if res.status() == 304 {
+            return Ok(None);
+        }
+
+        Self::from_response(url, res).map(Some)
+    }
+
+    fn from_response(url: &str, res: ureq::Response) -> Result<Db, FetchError> {
+        let features_url = format!("{url}/features.json");
+
+        // eg. HTML error page from a proxy or misconfigured mirror; missing header is tolerated
+        if let Some(content_type) = res.header("content-type") {
+            if !content_type.contains("json") {
+                return Err(FetchError::Decode {
+                    url: features_url,
+                    source: format!("response is {content_type} instead of JSON").into(),
+                });
+            }
+        }
+
+        // cache validators are kept so the next fetch can be conditional
+        let etag = res.header("etag").map(ToOwned::to_owned);
+        let last_modified = res.header("last-modified").map(ToOwned::to_owned);
+
+        // parsing while streaming the body overlaps it with the download and avoids buffering it
+        let reader = io::BufReader::new(LimitedReader::new(res.into_reader(), MAX_BODY_BYTES));
+
+        let mut db = Self::from_reader(reader).map_err(|err| {
+            match err.io_error_kind() {
+                // eg. read timed out or connection dropped mid-download
+                Some(io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) => FetchError::Timeout {
+                    secs: http_timeout_secs(),
+                },
+                Some(kind) if kind != io::ErrorKind::InvalidData => {
+                    FetchError::Network(Box::new(err))
+                }
+
+                // oversized body
+                Some(_) => FetchError::Decode {
+                    url: features_url,
+                    source: Box::new(err),
+                },
+
+                // invalid JSON or unexpected structure
+                None => FetchError::Decode {
+                    url: features_url,
+                    source: format!("the remote format may have changed: {err}").into(),
+                },
+            }
+        })?;
 
         db.base_url = url.to_owned();
+        db.etag = etag;
+        db.last_modified = last_modified;
 
-        // fill in slugs with map key
+        Ok(db)
+    }
+
+    /// Parses database in caniuse.rs's `features.json` format from a reader.
+    ///
+    /// The reader is not buffered; wrap it in a [`BufReader`](io::BufReader) if needed.
+    pub fn from_reader(reader: impl io::Read) -> Result<Db, serde_json::Error> {
+        // unknown fields are ignored so additions to the remote schema do not break parsing
+        let db = serde_json::from_reader(reader)?;
+        Ok(Self::with_slugs(db))
+    }
+
+    /// Parses database in caniuse.rs's `features.json` format from a string.
+    pub fn from_json_str(json: &str) -> Result<Db, serde_json::Error> {
+        let db = serde_json::from_str(json)?;
+        Ok(Self::with_slugs(db))
+    }
+
+    /// Fills in feature slugs with their keys in the feature map.
+    fn with_slugs(mut db: Db) -> Db {
         for (slug, feature) in &mut db.features {
             feature.slug = slug.clone()
         }
 
-        Ok(db)
+        db
     }
 
-    /// Returns an iterator of the most recent Rust versions in reverse chronological order.
-    pub fn versions_preview(&self) -> impl Iterator<Item = CompilerVersionData> {
-        let mut versions = self.versions.values().cloned().collect::<Vec<_>>();
-        versions.sort_by(|a, b| a.partial_cmp(b).unwrap().reverse());
-        versions.into_iter().take(10)
+    /// Overlays features and versions from `other` onto this database.
+    ///
+    /// Entries in `other` replace those with the same key and entries only in this database are
+    /// kept. The base URL and cache validators of this database are unchanged.
+    pub fn merge(&mut self, other: Db) {
+        self.versions.extend(other.versions);
+
+        for (slug, mut feature) in other.features {
+            feature.slug = slug.clone();
+            self.features.insert(slug, feature);
+        }
+
+        // index no longer reflects features
+        self.search_index = OnceLock::new();
+    }
+
+    /// Returns base URL this database was fetched from.
+    ///
+    /// Empty for databases that were not fetched, e.g. those parsed with
+    /// [`from_reader`](Self::from_reader).
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Returns all Rust versions in reverse chronological order.
+    pub fn all_versions(&self) -> Vec<&CompilerVersionData> {
+        let mut versions = self.versions.values().collect::<Vec<_>>();
+        versions.sort_by(|a, b| b.cmp(a));
+        versions
+    }
+
+    /// Returns all Rust versions of the given channel in reverse chronological order.
+    pub fn versions_in_channel(&self, channel: Channel) -> Vec<&CompilerVersionData> {
+        let mut versions = self.all_versions();
+        versions.retain(|version| version.channel == channel);
+        versions
+    }
+
+    /// Returns the most recent nightly Rust version, if any is known.
+    pub fn current_nightly(&self) -> Option<&CompilerVersionData> {
+        self.versions_in_channel(Channel::Nightly).first().copied()
+    }
+
+    /// Returns the most recent beta Rust version, if any is known.
+    pub fn current_beta(&self) -> Option<&CompilerVersionData> {
+        self.versions_in_channel(Channel::Beta).first().copied()
+    }
+
+    /// Returns the `count` most recent Rust versions in reverse chronological order.
+    pub fn recent_versions(&self, count: usize) -> Vec<&CompilerVersionData> {
+        let mut versions = self.all_versions();
+        versions.truncate(count);
+        versions
+    }
+
+    /// Finds version by its number, e.g. `1.70` or `1.70.0`.
+    ///
+    /// Numbers are compared numerically so that a trailing `.0` patch component is optional.
+    pub fn version(&self, number: &str) -> Option<&CompilerVersionData> {
+        if let Some(version) = self.versions.get(number) {
+            return Some(version);
+        }
+
+        let wanted = parse_version(number)?;
+        self.versions
+            .values()
+            .find(|version| version.version_tuple() == Some(wanted))
+    }
+
+    /// Returns the most recent Rust version, if any are known.
+    pub fn latest_version(&self) -> Option<&CompilerVersionData> {
+        self.versions.values().max()
+    }
+
+    /// Returns the total number of features in the database.
+    pub fn feature_count(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Returns the number of features that have been stabilized.
+    pub fn stable_feature_count(&self) -> usize {
+        self.features
+            .values()
+            .filter(|feature| StabilityFilter::Stable.matches(feature))
+            .count()
+    }
+
+    /// Returns an iterator of the `count` most recent stable Rust versions in reverse chronological
+    /// order.
+    ///
+    /// Beta and nightly versions are left out so they do not interleave with stable releases; use
+    /// [`versions_in_channel`](Self::versions_in_channel) to list them.
+    pub fn versions_preview(&self, count: usize) -> impl Iterator<Item = &CompilerVersionData> {
+        self.versions_in_channel(Channel::Stable)
+            .into_iter()
+            .take(count)
     }
 
     /// Finds a feature given it's slug and returns the feature and stabilization version data.
+    ///
+    /// If no feature has the given slug, features with an exactly matching alias or feature flag
+    /// are considered instead. When several match, the one with the alphabetically first slug is
+    /// returned.
     pub fn get_feature<'a>(
         &'a self,
         name: &str,
     ) -> Option<(&'a FeatureData, Option<&'a CompilerVersionData>)> {
-        let feature = self.features.get(name)?;
+        let feature = self.features.get(name).or_else(|| {
+            self.features
+                .values()
+                .filter(|feature| {
+                    feature.aliases.iter().any(|alias| alias == name)
+                        || feature.flag.as_deref() == Some(name)
+                })
+                .min_by(|a, b| a.slug.cmp(&b.slug))
+        })?;
 
         match feature.version_number.as_deref() {
             Some(v) => {
@@ -57,65 +314,589 @@ impl Db {
         }
     }
 
-    /// Fuzzy finds ~up to 20~ of the most relevant features in the database.
-    pub fn lookup<'a>(&'a self, query: &str) -> Vec<&'a FeatureData> {
-        let mut feats = vec![];
+    /// Finds all features associated with the given RFC number, ordered by slug.
+    pub fn get_feature_by_rfc(&self, id: u64) -> Vec<&FeatureData> {
+        let mut feats = self
+            .features
+            .values()
+            .filter(|feature| feature.rfc_id == Some(id))
+            .collect::<Vec<_>>();
 
-        // TODO: totally no logic to any of this
+        feats.sort_by(|a, b| a.slug.cmp(&b.slug));
 
-        for feature in self.features.values() {
-            if feature.slug.to_lowercase().contains(query) {
-                feats.push(feature);
-                continue;
-            }
+        feats
+    }
 
-            if feature
-                .flag
-                .as_deref()
-                .map(|flag| flag.to_lowercase().contains(query))
-                .unwrap_or(false)
-            {
-                feats.push(feature);
-                continue;
-            }
+    /// Finds all features tracked by the given rust-lang/rust issue number, ordered by slug.
+    pub fn get_feature_by_tracking_issue(&self, id: u64) -> Vec<&FeatureData> {
+        let mut feats = self
+            .features
+            .values()
+            .filter(|feature| feature.tracking_issue_id == Some(id))
+            .collect::<Vec<_>>();
 
-            if feature.title.to_lowercase().contains(query) {
-                feats.push(feature);
-                continue;
-            }
+        feats.sort_by(|a, b| a.slug.cmp(&b.slug));
 
-            for item in &feature.items {
-                if item.to_lowercase().contains(query) {
-                    feats.push(feature);
-                    continue;
-                }
-            }
+        feats
+    }
 
-            for item in &feature.aliases {
-                if item.to_lowercase().contains(query) {
-                    feats.push(feature);
-                    continue;
-                }
-            }
+    /// Finds all features implemented or stabilized by the given rust-lang/rust PR number, ordered by
+    /// slug.
+    pub fn get_features_by_pr(&self, id: u64) -> Vec<&FeatureData> {
+        let mut feats = self
+            .features
+            .values()
+            .filter(|feature| {
+                feature.impl_pr_id == Some(id) || feature.stabilization_pr_id == Some(id)
+            })
+            .collect::<Vec<_>>();
 
-            if strsim::sorensen_dice(query, &feature.slug.to_lowercase()) > 0.65 {
-                feats.push(feature);
-                continue;
-            }
+        feats.sort_by(|a, b| a.slug.cmp(&b.slug));
+
+        feats
+    }
+
+    /// Finds the feature with the given feature flag, ignoring case.
+    pub fn get_feature_by_flag(&self, flag: &str) -> Option<&FeatureData> {
+        self.features
+            .values()
+            .filter(|feature| {
+                feature
+                    .flag
+                    .as_deref()
+                    .is_some_and(|feature_flag| feature_flag.eq_ignore_ascii_case(flag))
+            })
+            .min_by(|a, b| a.slug.cmp(&b.slug))
+    }
+
+    /// Returns all features that have not been stabilized, ordered by title.
+    pub fn unstable_features(&self) -> Vec<&FeatureData> {
+        let mut feats = self
+            .features
+            .values()
+            .filter(|feature| StabilityFilter::Unstable.matches(feature))
+            .collect::<Vec<_>>();
+
+        feats.sort_by(|a, b| a.title.cmp(&b.title));
+
+        feats
+    }
+
+    /// Finds all features stabilized in the given version, ordered by title.
+    pub fn features_in_version(&self, version: &str) -> Vec<&FeatureData> {
+        let mut feats = self
+            .features
+            .values()
+            .filter(|feature| feature.version_number.as_deref() == Some(version))
+            .collect::<Vec<_>>();
+
+        feats.sort_by(|a, b| a.title.cmp(&b.title));
+
+        feats
+    }
+
+    /// Finds all features stabilized in or after the given version, newest version first.
+    ///
+    /// Features stabilized in the same version are ordered by title. Returns no features if
+    /// `version` is not a valid version number.
+    pub fn features_since(&self, version: &str) -> Vec<&FeatureData> {
+        let Some(since) = parse_version(version) else {
+            return Vec::new();
+        };
+
+        let mut feats = self
+            .features
+            .values()
+            .filter_map(|feature| {
+                let version = parse_version(feature.version_number.as_deref()?)?;
+                (version >= since).then_some((feature, version))
+            })
+            .collect::<Vec<_>>();
+
+        feats.sort_by(|(a, a_version), (b, b_version)| {
+            b_version.cmp(a_version).then_with(|| a.title.cmp(&b.title))
+        });
+
+        feats.into_iter().map(|(feature, _)| feature).collect()
+    }
+
+    /// Searches for features matching the query, most relevant first.
+    ///
+    /// This is the stable search API for library consumers; it does not depend on any Alfred
+    /// presentation logic. Matching is case-insensitive and all matches are returned.
+    pub fn search(&self, query: &str) -> Vec<&FeatureData> {
+        self.lookup(&query.to_lowercase(), usize::MAX)
+    }
+
+    /// Same as [`search`](Self::search) but also returns each feature's relevance score.
+    ///
+    /// Higher scores are more relevant. The integer part of a score denotes the kind of match: 4
+    /// for exact slug or item path matches, 3 for prefix matches, 2 for substring matches, 1 for
+    /// doc path matches and 0 for fuzzy matches. The fractional part is the similarity of the
+    /// query to the feature.
+    pub fn search_ranked(&self, query: &str) -> Vec<(&FeatureData, f64)> {
+        self.ranked_matches(&query.to_lowercase(), usize::MAX, StabilityFilter::Any)
+            .into_iter()
+            .map(|(feature, score, _)| (feature, score))
+            .collect()
+    }
+
+    /// Fuzzy finds up to `max` of the most relevant features in the database.
+    ///
+    /// Results are ordered by relevance, most relevant first. Features with equal scores are
+    /// ordered stable before unstable, then by stabilization version, newest first, and then
+    /// alphabetically by slug. Each feature appears at most once, no matter how many of its
+    /// fields match the query.
+    pub fn lookup<'a>(&'a self, query: &str, max: usize) -> Vec<&'a FeatureData> {
+        self.lookup_filtered(query, max, StabilityFilter::Any)
+    }
+
+    /// Same as [`lookup`](Self::lookup) but only considers features that pass the given filter.
+    pub fn lookup_filtered<'a>(
+        &'a self,
+        query: &str,
+        max: usize,
+        filter: StabilityFilter,
+    ) -> Vec<&'a FeatureData> {
+        self.lookup_matches(query, max, filter)
+            .into_iter()
+            .map(|(feature, _)| feature)
+            .collect()
+    }
+
+    /// Same as [`lookup_filtered`](Self::lookup_filtered) but also reports which field of each
+    /// feature matched the query.
+    pub fn lookup_matches<'a>(
+        &'a self,
+        query: &str,
+        max: usize,
+        filter: StabilityFilter,
+    ) -> Vec<(&'a FeatureData, MatchKind)> {
+        self.ranked_matches(query, max, filter)
+            .into_iter()
+            .map(|(feature, _, kind)| (feature, kind))
+            .collect()
+    }
+
+    /// Scores and ranks features, returning up to `max` of the most relevant along with their
+    /// scores and which field matched.
+    fn ranked_matches<'a>(
+        &'a self,
+        query: &str,
+        max: usize,
+        filter: StabilityFilter,
+    ) -> Vec<(&'a FeatureData, f64, MatchKind)> {
+        // indexed fields are normalized the same way so that e.g. "naïve" matches "naive"
+        let query = &search::normalize(query);
+
+        #[cfg(feature = "rayon")]
+        let entries = self.search_index().par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let entries = self.search_index().iter();
 
-            if let Some(flag) = feature.flag.as_deref() {
-                if strsim::sorensen_dice(query, &flag.to_lowercase()) > 0.65 {
-                    feats.push(feature);
-                    continue;
+        let mut feats = entries
+            // each feature is scored exactly once, so it can only be added once
+            .filter_map(|entry| {
+                let feature = self.features.get(entry.key())?;
+
+                if !filter.matches(feature) {
+                    return None;
                 }
-            }
 
-            if strsim::sorensen_dice(query, &feature.title.to_lowercase()) > 0.4 {
-                feats.push(feature);
-                continue;
+                let (score, kind) = entry.score(query)?;
+                Some((feature, score, kind))
+            })
+            .collect::<Vec<_>>();
+
+        // ties are broken deterministically so that result order is stable between runs
+        feats.sort_by(|(a, a_score, _), (b, b_score, _)| {
+            let a_version = a.version_number.as_deref().and_then(parse_version);
+            let b_version = b.version_number.as_deref().and_then(parse_version);
+
+            b_score
+                .partial_cmp(a_score)
+                .unwrap_or(Ordering::Equal)
+                // stable features, which have a version, first and newest first among those
+                .then_with(|| b_version.cmp(&a_version))
+                .then_with(|| a.slug.cmp(&b.slug))
+        });
+
+        // truncate after ranking so the most relevant matches are kept
+        feats.truncate(max);
+
+        feats
+    }
+
+    /// Returns search index, building it on first use.
+    pub(crate) fn search_index(&self) -> &[SearchEntry] {
+        self.search_index.get_or_init(|| {
+            self.features
+                .iter()
+                .map(|(key, feature)| SearchEntry::new(key, feature))
+                .collect()
+        })
+    }
+
+    /// Sets prebuilt search index, e.g. one restored from cache, so it is not rebuilt on first use.
+    pub(crate) fn set_search_index(&mut self, index: Vec<SearchEntry>) {
+        self.search_index = OnceLock::from(index);
+    }
+}
+
+/// Reader that fails once more than a given number of bytes have been read from it.
+///
+/// Guards against oversized bodies from misconfigured or malicious mirrors.
+struct LimitedReader<R> {
+    inner: R,
+    limit: u64,
+    remaining: u64,
+}
+
+impl<R> LimitedReader<R> {
+    fn new(inner: R, limit: u64) -> Self {
+        Self {
+            inner,
+            limit,
+            remaining: limit,
+        }
+    }
+}
+
+impl<R: io::Read> io::Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // reading one byte past the limit distinguishes a body of exactly the limit from an
+        // oversized one
+        let max = usize::try_from(self.remaining + 1).map_or(buf.len(), |max| max.min(buf.len()));
+        let n = self.inner.read(&mut buf[..max])?;
+
+        if n as u64 > self.remaining {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("response exceeds {} MiB limit", self.limit / 1024 / 1024),
+            ));
+        }
+
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// Builds HTTP agent with connect and read timeouts and proxy configuration for the given URL.
+///
+/// The timeout is read from `CANIUSE_HTTP_TIMEOUT_SECS`, defaulting to 10 seconds.
+fn http_agent(url: &str) -> ureq::Agent {
+    let timeout = Duration::from_secs(http_timeout_secs());
+
+    http::agent_builder(url)
+        .timeout_connect(timeout)
+        .timeout_read(timeout)
+        .build()
+}
+
+fn http_timeout_secs() -> u64 {
+    env::var("CANIUSE_HTTP_TIMEOUT_SECS")
+        .ok()
+        .and_then(|secs| secs.trim().parse().ok())
+        .unwrap_or(DEFAULT_HTTP_TIMEOUT_SECS)
+}
+
+/// Builds request for the features database at the given base URL.
+fn features_request(agent: &ureq::Agent, url: &str) -> ureq::Request {
+    agent
+        .get(&format!("{url}/features.json"))
+        .set("user-agent", &format!("{UA_NAME}/{UA_VERSION}"))
+}
+
+/// Converts request error into typed fetch error.
+fn fetch_error(url: &str, err: ureq::Error) -> FetchError {
+    if let ureq::Error::Status(status, _) = err {
+        return FetchError::Http {
+            url: format!("{url}/features.json"),
+            status,
+        };
+    }
+
+    if is_timeout(&err) {
+        return FetchError::Timeout {
+            secs: http_timeout_secs(),
+        };
+    }
+
+    FetchError::Network(Box::new(err))
+}
+
+fn is_timeout(err: &ureq::Error) -> bool {
+    let ureq::Error::Transport(transport) = err else {
+        return false;
+    };
+
+    transport
+        .source()
+        .and_then(|source| source.downcast_ref::<io::Error>())
+        .is_some_and(|err| {
+            matches!(
+                err.kind(),
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+            )
+        })
+}
+
+/// Sends request for the features database at `url`, retrying with exponential backoff on
+/// transient errors.
+///
+/// The number of retries is read from `CANIUSE_FETCH_RETRIES`, defaulting to 3 and capped at 5, and
+/// each delay is capped at 2 seconds so a failing fetch cannot stall the workflow for long. The
+/// last error is returned if all attempts fail.
+fn call_with_retry(
+    url: &str,
+    make_req: impl Fn() -> ureq::Request,
+) -> Result<ureq::Response, FetchError> {
+    let retries = env::var("CANIUSE_FETCH_RETRIES")
+        .ok()
+        .and_then(|retries| retries.trim().parse().ok())
+        .unwrap_or(DEFAULT_FETCH_RETRIES)
+        .min(MAX_FETCH_RETRIES);
+
+    let mut attempt = 0;
+
+    loop {
+        match make_req().call() {
+            Err(err) if attempt < retries && is_transient(&err) => {
+                let delay = 2_u32
+                    .checked_pow(attempt)
+                    .and_then(|factor| RETRY_BASE_DELAY.checked_mul(factor))
+                    .map_or(MAX_RETRY_DELAY, |delay| delay.min(MAX_RETRY_DELAY));
+
+                log::warn!(
+                    "fetch attempt {} failed, retrying in {delay:?}: {err}",
+                    attempt + 1
+                );
+
+                thread::sleep(delay);
+                attempt += 1;
             }
+
+            res => return res.map_err(|err| fetch_error(url, err)),
         }
+    }
+}
 
-        feats
+/// Returns true for errors that may succeed on retry, i.e., server errors and network failures.
+fn is_transient(err: &ureq::Error) -> bool {
+    match err {
+        ureq::Error::Status(status, _) => *status >= 500,
+        ureq::Error::Transport(transport) => matches!(
+            transport.kind(),
+            ureq::ErrorKind::Io | ureq::ErrorKind::ConnectionFailed
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn db(json: serde_json::Value) -> Db {
+        Db::from_json_str(&json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn search_ranked_scores() {
+        let db = db(json!({
+            "features": {
+                "let_else": { "title": "let else", "version": "1.65" },
+                "mem_swap": { "title": "swap", "version": "1.0" },
+                "option_zip": {
+                    "title": "Option::zip",
+                    "items": ["core::option::Option::zip"],
+                    "version": "1.46",
+                },
+            },
+        }));
+
+        let score = |query: &str, slug: &str| {
+            db.search_ranked(query)
+                .into_iter()
+                .find(|(feature, _)| feature.slug == slug)
+                .map(|(_, score)| score)
+                .unwrap()
+        };
+
+        // exact slug match with identical slug
+        assert_eq!(score("let_else", "let_else"), 4.99);
+
+        // exact item path match
+        assert_eq!(score("option::zip", "option_zip"), 4.99);
+
+        // title prefix match, even when whole title matches, is below any exact match
+        assert_eq!(score("swap", "mem_swap"), 3.99);
+
+        // substring match; similarity ignores whitespace so is 2 * 3 / (3 + 6) bigrams of "letelse"
+        assert_eq!(score("else", "let_else"), 2.66);
+    }
+
+    #[test]
+    fn lookup_lists_feature_once_when_several_fields_match() {
+        let db = db(json!({
+            "features": {
+                "async_await": {
+                    "title": "async/await",
+                    "items": ["async fn", "async {}"],
+                    "version": "1.39",
+                },
+            },
+        }));
+
+        let results = db.lookup("async", 20);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].slug, "async_await");
+    }
+
+    fn stability_db() -> Db {
+        db(json!({
+            "features": {
+                "async_await": { "title": "async/await", "version": "1.39" },
+                "async_closure": { "title": "async closures", "flag": "async_closure" },
+                "async_drop": { "title": "async drop", "flag": "async_drop" },
+                "async_fn_in_trait": { "title": "async fn in trait", "version": "1.75" },
+            },
+        }))
+    }
+
+    #[test]
+    fn lookup_filtered_unstable() {
+        let db = stability_db();
+
+        let results = db.lookup_filtered("async", 20, StabilityFilter::Unstable);
+        assert_eq!(results.len(), 2);
+        assert!(results
+            .iter()
+            .all(|feature| feature.version_number.is_none()));
+    }
+
+    #[test]
+    fn lookup_filtered_stable_excludes_unstable() {
+        let db = stability_db();
+
+        let results = db.lookup_filtered("async", 20, StabilityFilter::Stable);
+        assert_eq!(results.len(), 2);
+        assert!(results
+            .iter()
+            .all(|feature| feature.version_number.is_some()));
+        assert!(!results
+            .iter()
+            .any(|feature| feature.slug == "async_closure"));
+    }
+
+    #[test]
+    fn all_versions_with_missing_or_malformed_release_dates() {
+        let db = db(json!({
+            "versions": {
+                "1.70": { "number": "1.70", "release_date": "2023-06-01" },
+                "1.71": { "number": "1.71", "release_date": "soon" },
+                "1.72": { "number": "1.72" },
+                "1.69": { "number": "1.69", "release_date": "2023-04-20" },
+            },
+        }));
+
+        let numbers = db
+            .all_versions()
+            .into_iter()
+            .map(|version| version.number.as_str())
+            .collect::<Vec<_>>();
+
+        // versions without a usable date are still ordered by version number
+        assert_eq!(numbers, ["1.72", "1.71", "1.70", "1.69"]);
+    }
+
+    #[test]
+    fn get_feature_by_alias_or_flag() {
+        let db = db(json!({
+            "features": {
+                "let_else": { "title": "let else", "aliases": ["let-else", "guard let"] },
+                "if_let_guard": { "title": "if let guards", "aliases": ["guard let"] },
+                "try_blocks": { "title": "try blocks", "flag": "try_blocks_v2" },
+            },
+        }));
+
+        let slug = |name: &str| {
+            db.get_feature(name)
+                .map(|(feature, _)| feature.slug.as_str())
+        };
+
+        assert_eq!(slug("let_else"), Some("let_else"));
+        assert_eq!(slug("let-else"), Some("let_else"));
+        assert_eq!(slug("try_blocks_v2"), Some("try_blocks"));
+        assert_eq!(slug("let"), None);
+
+        // alias shared by several features resolves to alphabetically first slug
+        assert_eq!(slug("guard let"), Some("if_let_guard"));
+    }
+
+    #[test]
+    fn lookup_orders_ties_deterministically() {
+        let db = db(json!({
+            "features": {
+                "c_unstable": { "title": "iterator adapters" },
+                "b_old": { "title": "iterator adapters", "version": "1.10" },
+                "d_new": { "title": "iterator adapters", "version": "1.60" },
+                "a_new": { "title": "iterator adapters", "version": "1.60" },
+            },
+        }));
+
+        let slugs = |db: &Db| {
+            db.lookup("iter", 20)
+                .into_iter()
+                .map(|feature| feature.slug.clone())
+                .collect::<Vec<_>>()
+        };
+
+        // stable before unstable, then newest version first, then by slug
+        let first = slugs(&db);
+        assert_eq!(first, ["a_new", "d_new", "b_old", "c_unstable"]);
+
+        assert_eq!(slugs(&db), first);
+    }
+
+    #[test]
+    fn merge_adds_replaces_and_keeps() {
+        let mut base = db(json!({
+            "versions": { "1.65": { "number": "1.65" } },
+            "features": {
+                "let_else": { "title": "let else", "version": "1.65" },
+                "try_blocks": { "title": "try blocks" },
+            },
+        }));
+
+        // builds search index before merging
+        assert!(base.lookup("labeled", 20).is_empty());
+
+        base.merge(db(json!({
+            "versions": { "1.66": { "number": "1.66" } },
+            "features": {
+                "try_blocks": { "title": "try blocks", "version": "1.66" },
+                "label_break_value": { "title": "labeled block breaks", "version": "1.65" },
+            },
+        })));
+
+        // kept
+        assert_eq!(base.get_feature("let_else").unwrap().0.title, "let else");
+
+        // replaced
+        let (try_blocks, version) = base.get_feature("try_blocks").unwrap();
+        assert_eq!(try_blocks.version_number.as_deref(), Some("1.66"));
+        assert_eq!(version.unwrap().number, "1.66");
+
+        // added, with slug filled in and searchable
+        assert_eq!(
+            base.get_feature("label_break_value").unwrap().0.slug,
+            "label_break_value"
+        );
+        assert_eq!(base.lookup("labeled", 20)[0].slug, "label_break_value");
+
+        assert_eq!(base.feature_count(), 3);
+        assert_eq!(base.all_versions().len(), 2);
     }
 }
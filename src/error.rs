@@ -0,0 +1,63 @@
+//! Typed errors for library consumers.
+
+use std::{error::Error as StdError, fmt};
+
+/// Error fetching the feature database.
+///
+/// Distinguishes errors worth retrying later, i.e., network errors and timeouts, from those caused
+/// by the server or the data it returned.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FetchError {
+    /// Server could not be reached or the connection failed.
+    Network(Box<dyn StdError + Send + Sync>),
+
+    /// Server responded with an error status code.
+    Http {
+        /// URL of the feature database.
+        url: String,
+
+        /// HTTP status code of the response.
+        status: u16,
+    },
+
+    /// Response is not a valid feature database.
+    Decode {
+        /// URL of the feature database.
+        url: String,
+
+        /// Reason the response could not be decoded.
+        source: Box<dyn StdError + Send + Sync>,
+    },
+
+    /// Server did not respond in time.
+    Timeout {
+        /// Timeout that was exceeded, in seconds.
+        secs: u64,
+    },
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Network(err) => write!(f, "failed to fetch feature database: {err}"),
+            FetchError::Http { url, status } => write!(f, "{url} returned {status}"),
+            FetchError::Decode { url, source } => {
+                write!(f, "failed to parse feature database from {url}: {source}")
+            }
+            FetchError::Timeout { secs } => {
+                write!(f, "timed out fetching feature database after {secs}s")
+            }
+        }
+    }
+}
+
+impl StdError for FetchError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            FetchError::Network(err) => Some(err.as_ref()),
+            FetchError::Decode { source, .. } => Some(source.as_ref()),
+            FetchError::Http { .. } | FetchError::Timeout { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Shared HTTP client configuration.
+
+use std::env;
+
+/// Returns true if network access is disabled by setting `CANIUSE_OFFLINE`.
+///
+/// Any value other than empty, "0" or "false" enables offline mode.
+pub fn offline_mode() -> bool {
+    env::var("CANIUSE_OFFLINE").is_ok_and(|val| {
+        let val = val.trim();
+        !(val.is_empty() || val == "0" || val.eq_ignore_ascii_case("false"))
+    })
+}
+
+/// Returns agent builder configured to use proxies from the standard environment variables.
+///
+/// Proxies are read from `ALL_PROXY`, `HTTPS_PROXY` and `HTTP_PROXY` (or their lowercase forms)
+/// unless the host of `url` is excluded by `NO_PROXY`.
+pub(crate) fn agent_builder(url: &str) -> ureq::AgentBuilder {
+    let use_proxy = !url_host(url).is_some_and(is_no_proxy_host);
+    ureq::builder().try_proxy_from_env(use_proxy)
+}
+
+/// Returns true if host matches any entry in the `NO_PROXY` environment variable.
+fn is_no_proxy_host(host: &str) -> bool {
+    let Some(no_proxy) = env::var("NO_PROXY").or_else(|_| env::var("no_proxy")).ok() else {
+        return false;
+    };
+
+    no_proxy
+        .split(',')
+        .map(|entry| entry.trim().trim_start_matches('.'))
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            entry == "*"
+                || host.eq_ignore_ascii_case(entry)
+                || host
+                    .to_lowercase()
+                    .ends_with(&format!(".{}", entry.to_lowercase()))
+        })
+}
+
+/// Extracts host from URL without its port, e.g. "caniuse.rs" from "https://caniuse.rs/foo".
+fn url_host(url: &str) -> Option<&str> {
+    let (_scheme, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+
+    // strip any user info and port
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+
+    (!host.is_empty()).then_some(host)
+}
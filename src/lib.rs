@@ -1,4 +1,9 @@
 //! Caniuse
+//!
+//! Rust feature support data from [caniuse.rs](https://caniuse.rs).
+//!
+//! Use [`Db::search`] and [`Db::recent_versions`] to query the feature database without any
+//! Alfred-specific presentation.
 
 #![deny(rust_2018_idioms, nonstandard_style, future_incompatible)]
 #![warn(missing_docs, clippy::uninlined_format_args)]
@@ -7,14 +12,22 @@ use std::{fmt, io, process};
 
 mod cache;
 mod db;
+mod error;
+mod http;
 mod models;
+mod overrides;
+mod search;
 mod update;
 
 pub use self::{
-    cache::{cache_fetch, cache_put},
-    db::Db,
-    models::{CompilerVersionData, FeatureData},
-    update::self_update_check_item,
+    cache::{cache_clear, cache_dir, cache_fetch, cache_put, cache_touch, Cached},
+    db::{Db, StabilityFilter},
+    error::FetchError,
+    http::offline_mode,
+    models::{Channel, CompilerVersionData, FeatureData},
+    overrides::overrides_fetch,
+    search::MatchKind,
+    update::{self_update_check_item, self_version_item},
 };
 
 /// Crate Alfred readable error row.
@@ -25,6 +38,20 @@ pub fn alfred_error(err: impl fmt::Display + 'static) -> alfred::Item<'static> {
         .into_item()
 }
 
+/// Crate Alfred readable error row that opens `url` when actioned.
+///
+/// Useful for errors the user can act on, e.g. by checking a service's status.
+pub fn alfred_error_with_action(
+    err: impl fmt::Display + 'static,
+    url: impl Into<String>,
+) -> alfred::Item<'static> {
+    alfred::ItemBuilder::new("error")
+        .subtitle(err.to_string())
+        .arg(url.into())
+        .valid(true)
+        .into_item()
+}
+
 /// Output Alfred readable error row to stdout and exit.
 pub fn exit_alfred_error(err: impl fmt::Display + 'static) -> ! {
     alfred::json::write_items(io::stdout(), &[alfred_error(err)]).unwrap();
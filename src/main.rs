@@ -1,62 +1,398 @@
 #![deny(rust_2018_idioms, nonstandard_style, future_incompatible)]
 #![warn(clippy::uninlined_format_args)]
 
-use std::{env, io};
+use std::{
+    env,
+    io::{self, Write as _},
+    process,
+};
 
-use alfred_caniuse_rs::{cache_fetch, cache_put, exit_alfred_error, self_update_check_item, Db};
+use alfred_caniuse_rs::{
+    alfred_error, alfred_error_with_action, cache_clear, cache_dir, cache_fetch, cache_put,
+    cache_touch, exit_alfred_error, offline_mode, overrides_fetch, self_update_check_item,
+    self_version_item, Cached, Channel, Db, FeatureData, FetchError, MatchKind, StabilityFilter,
+};
 use eyre::eyre;
 
 const CANIUSE_URL: &str = "https://caniuse.rs";
+const MAX_RESULTS: usize = 20;
+const DEFAULT_RECENT_COUNT: usize = 10;
 
 fn main() {
-    let res = try_main().and_then(|items| Ok(alfred::json::write_items(io::stdout(), &items)?));
+    init_logger();
+
+    let mut args = env::args().peekable();
+    // skip self binary arg
+    args.next();
+
+    // scripting modes output plain data instead of Alfred items
+    let script_main: Option<fn(Option<String>) -> eyre::Result<()>> =
+        match args.peek().map(String::as_str) {
+            Some("--json") => Some(json_main),
+            Some("--text") => Some(text_main),
+            _ => None,
+        };
+
+    if let Some(script_main) = script_main {
+        args.next();
+
+        if let Err(err) = script_main(args.next()) {
+            eprintln!("error: {err}");
+            process::exit(1);
+        }
+
+        return;
+    }
+
+    let res = try_main(args.next())
+        .and_then(|items| Ok(alfred::json::write_items(io::stdout(), &items)?));
 
     if let Err(err) = res {
         exit_alfred_error(err);
     }
 }
 
-fn try_main() -> eyre::Result<Vec<alfred::Item<'static>>> {
+fn try_main(query: Option<String>) -> eyre::Result<Vec<alfred::Item<'static>>> {
     let mut items = vec![];
 
+    let query = query.as_deref().map(normalize_query).unwrap_or_default();
+
+    // commands run before database is loaded so broken cache state can be inspected or cleared
+    match query.as_str() {
+        "!clearcache" => {
+            cache_clear()?;
+
+            items.push(
+                alfred::ItemBuilder::new("Cache cleared")
+                    .subtitle("Feature database will be fetched again on next query")
+                    .valid(false)
+                    .into_item(),
+            );
+
+            return Ok(items);
+        }
+
+        "!cachedir" => {
+            let dir = cache_dir().display().to_string();
+
+            items.push(
+                alfred::ItemBuilder::new(dir.clone())
+                    .subtitle("Press enter to reveal cache directory in Finder")
+                    .arg(format!("file://{dir}"))
+                    .text_copy(dir)
+                    .into_item(),
+            );
+
+            return Ok(items);
+        }
+
+        "!version" => {
+            items.push(self_version_item());
+            return Ok(items);
+        }
+
+        _ => {}
+    }
+
     // check for workflow update and add row if needed
     items.extend(self_update_check_item());
 
-    let mut args = env::args();
-    // skip self binary arg
-    args.next();
+    let base_url = caniuse_url();
+    let (mut db, stale) = match load_db(&base_url) {
+        Ok(res) => res,
 
-    let db = match cache_fetch() {
-        Some(db) => db,
-        None => {
-            let db = Db::fetch(CANIUSE_URL)?;
-            cache_put(&db);
-            db
+        Err(err) => {
+            log::error!("failed to load feature database: {err}");
+
+            // eg. no internet connection or site is down; opening the site helps tell which
+            let is_network_error = match err.downcast_ref::<FetchError>() {
+                Some(FetchError::Network(_) | FetchError::Timeout { .. }) => true,
+                Some(FetchError::Http { status, .. }) => *status >= 500,
+                _ => false,
+            };
+
+            if is_network_error {
+                items.push(alfred_error_with_action(err, base_url));
+            } else {
+                items.push(alfred_error(err));
+            }
+
+            return Ok(items);
         }
     };
 
-    match args.next() {
-        None => show_recent_versions(&db, &mut items),
-        Some(query) if query.is_empty() => show_recent_versions(&db, &mut items),
+    // overrides are merged after caching so the cache only ever holds upstream data
+    match overrides_fetch() {
+        Ok(Some(overrides)) => db.merge(overrides),
+        Ok(None) => {}
 
-        Some(query) => match_query(&db, &query.to_lowercase(), &mut items),
+        // upstream data is still usable so results are shown alongside the error
+        Err(err) => items.push(alfred_error(err)),
+    }
+
+    if stale {
+        items.push(
+            alfred::ItemBuilder::new("⚠️ Showing cached data (may be outdated)")
+                .subtitle("Feature database could not be refreshed")
+                .valid(false)
+                .into_item(),
+        );
+    }
+
+    match query.as_str() {
+        "" => show_recent_versions(&db, &mut items),
+        "!stats" => show_stats(&db, &mut items),
+        "!unstable" => {
+            let features = db.unstable_features().into_iter().take(MAX_RESULTS);
+            items.extend(features.map(|feat| feat.to_alfred_item(&base_url, None)));
+            Ok(())
+        }
+        "!versions" => {
+            items.extend(db.all_versions().into_iter().map(|v| v.to_alfred_item()));
+            Ok(())
+        }
+        query => match_query(&db, &base_url, query, &mut items),
     }?;
 
     Ok(items)
 }
 
+/// Writes matched features, or recent versions when query is empty, to stdout as JSON.
+fn json_main(query: Option<String>) -> eyre::Result<()> {
+    let (mut db, _) = load_db(&caniuse_url())?;
+
+    if let Some(overrides) = overrides_fetch()? {
+        db.merge(overrides);
+    }
+
+    let stdout = io::stdout().lock();
+
+    match query.as_deref().map(normalize_query) {
+        Some(query) if !query.is_empty() => {
+            let features = find_features(&db, &query)?
+                .into_iter()
+                .map(|(feature, _)| feature)
+                .collect::<Vec<_>>();
+            serde_json::to_writer_pretty(stdout, &features)?;
+        }
+
+        _ => {
+            let versions = db.versions_preview(recent_count()).collect::<Vec<_>>();
+            serde_json::to_writer_pretty(stdout, &versions)?;
+        }
+    }
+
+    println!();
+
+    Ok(())
+}
+
+/// Writes matched features, or recent versions when query is empty, to stdout as lines of text.
+fn text_main(query: Option<String>) -> eyre::Result<()> {
+    let base_url = caniuse_url();
+    let (mut db, _) = load_db(&base_url)?;
+
+    if let Some(overrides) = overrides_fetch()? {
+        db.merge(overrides);
+    }
+
+    let mut stdout = io::stdout().lock();
+
+    match query.as_deref().map(normalize_query) {
+        Some(query) if !query.is_empty() => {
+            for (feat, _) in find_features(&db, &query)? {
+                let version = db.get_feature(&feat.slug).and_then(|(_, version)| version);
+
+                writeln!(
+                    stdout,
+                    "{} — {} — {base_url}/features/{}",
+                    feat.title,
+                    feat.subtitle(version),
+                    feat.slug,
+                )?;
+            }
+        }
+
+        _ => {
+            for version in db.versions_preview(recent_count()) {
+                write!(stdout, "v{} ({})", version.number, version.channel)?;
+
+                if let Some(release_date) = version.release_date.as_deref() {
+                    write!(stdout, " — released {release_date}")?;
+                }
+
+                writeln!(stdout)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads feature database from cache, fetching it from `base_url` if needed.
+///
+/// Also returns true if the database is an expired cache that could not be refreshed.
+fn load_db(base_url: &str) -> eyre::Result<(Db, bool)> {
+    // cached data is used regardless of age since it can not be refreshed
+    if offline_mode() {
+        return match cache_fetch() {
+            Cached::Fresh(db) | Cached::Expired(db) => Ok((db, false)),
+            Cached::Missing => Err(eyre!(
+                "offline mode is on (CANIUSE_OFFLINE) and no cached feature database is present"
+            )),
+        };
+    }
+
+    // cache from a different mirror is not reused, nor are its validators sent to this one
+    let cached = match cache_fetch() {
+        Cached::Fresh(db) | Cached::Expired(db) if db.base_url() != base_url => Cached::Missing,
+        cached => cached,
+    };
+
+    let res = match cached {
+        Cached::Fresh(db) => (db, false),
+
+        Cached::Expired(cached_db) => match Db::fetch_if_modified(base_url, &cached_db) {
+            Ok(Some(db)) => {
+                cache_put(&db);
+                (db, false)
+            }
+
+            // remote is unchanged so cached copy only needs its freshness bumped
+            Ok(None) => {
+                cache_touch();
+                (cached_db, false)
+            }
+
+            // eg. no internet connection; slightly outdated data is better than none
+            Err(err) => {
+                log::warn!("failed to refresh feature database: {err}");
+                log::warn!("using expired cache; data may be outdated");
+                (cached_db, true)
+            }
+        },
+
+        Cached::Missing => {
+            let db = Db::fetch(base_url)?;
+            cache_put(&db);
+            (db, false)
+        }
+    };
+
+    Ok(res)
+}
+
 fn show_recent_versions(db: &Db, items: &mut Vec<alfred::Item<'static>>) -> eyre::Result<()> {
-    let versions = db.versions_preview().map(|v| v.to_alfred_item());
+    // informational row to make query syntax discoverable; does nothing when actioned
+    items.push(
+        alfred::ItemBuilder::new("Type a feature name, rfc:NNNN, or a version")
+            .subtitle("Also try issue:NNNNN, stable:query or unstable:query")
+            .valid(false)
+            .into_item(),
+    );
+
+    // live pre-release channels come first, followed by stable releases
+    let channels = [db.current_nightly(), db.current_beta()];
+    items.extend(channels.into_iter().flatten().map(|v| v.to_alfred_item()));
+
+    let versions = db
+        .versions_preview(recent_count())
+        .map(|v| v.to_alfred_item());
     items.extend(versions);
 
     Ok(())
 }
 
-fn match_query(db: &Db, query: &str, items: &mut Vec<alfred::Item<'static>>) -> eyre::Result<()> {
-    let features = db.lookup(query);
+fn show_stats(db: &Db, items: &mut Vec<alfred::Item<'static>>) -> eyre::Result<()> {
+    let total = db.feature_count();
+    let stable = db.stable_feature_count();
+
+    items.push(
+        alfred::ItemBuilder::new(format!("{total} features"))
+            .subtitle(format!("{stable} stable · {} unstable", total - stable))
+            .valid(false)
+            .into_item(),
+    );
+
+    if let Some(version) = db.latest_version() {
+        items.push(
+            alfred::ItemBuilder::new(format!("Latest version: v{}", version.number))
+                .subtitle(format!("{} channel", version.channel))
+                .valid(false)
+                .into_item(),
+        );
+    }
+
+    Ok(())
+}
+
+fn match_query(
+    db: &Db,
+    base_url: &str,
+    query: &str,
+    items: &mut Vec<alfred::Item<'static>>,
+) -> eyre::Result<()> {
+    if let Some(channel) = query.strip_prefix("channel:") {
+        // eg. a channel name that is still being typed; other rows are kept
+        let channel = match channel.parse::<Channel>() {
+            Ok(channel) => channel,
+            Err(err) => {
+                items.push(alfred_error(err));
+                return Ok(());
+            }
+        };
+
+        let versions = db.versions_in_channel(channel);
+        items.extend(versions.into_iter().map(|v| v.to_alfred_item()));
+
+        return Ok(());
+    }
+
+    // exact version numbers get a release overview above the features stabilized in it
+    if let Some(version) = is_version(query).then(|| db.version(query)).flatten() {
+        let features = db.features_in_version(&version.number);
+
+        let count = match features.len() {
+            1 => "1 feature stabilized".to_owned(),
+            n => format!("{n} features stabilized"),
+        };
+
+        let mut builder = version.to_alfred_item_builder();
+        builder.set_subtitle(match version.subtitle() {
+            Some(subtitle) => format!("{subtitle} · {count}"),
+            None => count,
+        });
+        items.push(builder.into_item());
+
+        items.extend(
+            features
+                .into_iter()
+                .map(|feat| feat.to_alfred_item(base_url, Some(version))),
+        );
+
+        return Ok(());
+    }
 
+    // eg. an RFC number or version that is still being typed; other rows are kept
+    let features = match find_features(db, query) {
+        Ok(features) => features,
+        Err(err) => {
+            items.push(alfred_error(err));
+            return Ok(());
+        }
+    };
+
+    // an empty result is not an error; informational row does nothing when actioned
     if features.is_empty() {
-        return Err(eyre!("no feature match"));
+        items.push(
+            alfred::ItemBuilder::new(format!("No features match '{query}'"))
+                .subtitle("Press tab to clear query")
+                .autocomplete("")
+                .valid(false)
+                .into_item(),
+        );
+
+        return Ok(());
     }
 
     // let (feature, _) = db
@@ -66,11 +402,174 @@ fn match_query(db: &Db, query: &str, items: &mut Vec<alfred::Item<'static>>) ->
     // let item = feature.to_alfred_item(CANIUSE_URL);
     // items.push(item);
 
-    items.extend(
+    items.extend(features.into_iter().map(|(feat, kind)| {
+        let version = db.get_feature(&feat.slug).and_then(|(_, version)| version);
+        let mut builder = feat.to_alfred_item_builder(base_url, version);
+
+        // show alias that matched so it's clear why the feature is listed
+        if let Some(MatchKind::Alias(idx)) = kind {
+            builder.set_title(format!("{} ({})", feat.aliases[idx], feat.title));
+        }
+
+        // matched item is listed first so it stands out in large type
+        if let Some(MatchKind::Item(idx)) = kind {
+            builder.set_text_large_type(feat.items_text(Some(idx)));
+        }
+
+        // explain less obvious matches
+        if let Some(description) = kind.and_then(|kind| kind.describe(feat)) {
+            builder.set_subtitle(format!("{} · {description}", feat.subtitle(version)));
+        }
+
+        builder.into_item()
+    }));
+
+    Ok(())
+}
+
+/// Finds features matching query, taking query prefixes into account.
+///
+/// For fuzzy searches, the field that matched is returned alongside each feature.
+fn find_features<'a>(
+    db: &'a Db,
+    query: &str,
+) -> eyre::Result<Vec<(&'a FeatureData, Option<MatchKind>)>> {
+    let without_kind = |features: Vec<&'a FeatureData>| {
         features
             .into_iter()
-            .map(|feat| feat.to_alfred_item(CANIUSE_URL)),
-    );
+            .map(|feature| (feature, None))
+            .collect::<Vec<_>>()
+    };
 
-    Ok(())
+    let with_kind = |features: Vec<(&'a FeatureData, MatchKind)>| {
+        features
+            .into_iter()
+            .map(|(feature, kind)| (feature, Some(kind)))
+            .collect::<Vec<_>>()
+    };
+
+    let features = if let Some(id) = query.strip_prefix("rfc:") {
+        without_kind(db.get_feature_by_rfc(parse_id(id, "RFC")?))
+    } else if let Some(id) = query.strip_prefix("issue:") {
+        without_kind(db.get_feature_by_tracking_issue(parse_id(id, "issue")?))
+    } else if let Some(id) = query.strip_prefix("pr:") {
+        without_kind(db.get_features_by_pr(parse_id(id, "PR")?))
+    } else if let Some(flag) = query.strip_prefix("flag:") {
+        without_kind(db.get_feature_by_flag(flag.trim()).into_iter().collect())
+    } else if let Some(version) = query.strip_prefix("since:") {
+        let version = version.trim();
+
+        if !is_version(version) {
+            return Err(eyre!("invalid version number: {version}"));
+        }
+
+        without_kind(db.features_since(version))
+    } else if let Some(query) = query.strip_prefix("stable:") {
+        with_kind(db.lookup_matches(query.trim(), MAX_RESULTS, StabilityFilter::Stable))
+    } else if let Some(query) = query.strip_prefix("unstable:") {
+        with_kind(db.lookup_matches(query.trim(), MAX_RESULTS, StabilityFilter::Unstable))
+    } else if is_version(query) {
+        let number = db
+            .version(query)
+            .map_or(query, |version| version.number.as_str());
+        without_kind(db.features_in_version(number))
+    } else {
+        with_kind(db.lookup_matches(query, MAX_RESULTS, StabilityFilter::Any))
+    };
+
+    Ok(features)
+}
+
+/// Minimal logger writing to stderr, which Alfred shows in its debug console.
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs stderr logger with level read from `CANIUSE_LOG` or `RUST_LOG` if set.
+///
+/// Defaults to warn level when unset or invalid so that normal runs are quiet.
+fn init_logger() {
+    static LOGGER: StderrLogger = StderrLogger;
+
+    let level = env::var("CANIUSE_LOG")
+        .or_else(|_| env::var("RUST_LOG"))
+        .ok()
+        .and_then(|level| level.trim().parse().ok())
+        .unwrap_or(log::LevelFilter::Warn);
+
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// Returns caniuse.rs base URL, read from `CANIUSE_BASE_URL` if set.
+fn caniuse_url() -> String {
+    env::var("CANIUSE_BASE_URL")
+        .ok()
+        .map(|url| url.trim().trim_end_matches('/').to_owned())
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| CANIUSE_URL.to_owned())
+}
+
+/// Returns number of recent versions to show, read from `CANIUSE_RECENT_COUNT` if set.
+fn recent_count() -> usize {
+    env::var("CANIUSE_RECENT_COUNT")
+        .ok()
+        .and_then(|count| count.trim().parse().ok())
+        .unwrap_or(DEFAULT_RECENT_COUNT)
+}
+
+/// Lowercases query, trims it and collapses internal runs of whitespace into single spaces.
+fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Parses numeric ID from query prefix argument, e.g. "2394" from "rfc:2394".
+fn parse_id(id: &str, kind: &str) -> eyre::Result<u64> {
+    id.trim()
+        .parse()
+        .map_err(|_| eyre!("invalid {kind} number: {id}"))
+}
+
+/// Returns true if query looks like a version number, e.g. "1.65" or "1.65.0".
+///
+/// Plain numbers without a dot are not considered versions so they still go through fuzzy search.
+fn is_version(query: &str) -> bool {
+    query.contains('.')
+        && query
+            .split('.')
+            .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_query_trims_and_collapses_whitespace() {
+        assert_eq!(normalize_query(" Async "), "async");
+        assert_eq!(normalize_query("  let \t  ELSE\n"), "let else");
+    }
+
+    #[test]
+    fn normalize_query_whitespace_only_is_empty() {
+        assert_eq!(normalize_query("\t"), "");
+        assert_eq!(normalize_query(" \t \t"), "");
+    }
 }
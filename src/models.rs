@@ -2,20 +2,38 @@
 //!
 //! Definitions derived from https://github.com/jplatte/caniuse.rs/blob/e9c940047437cccfaf8ff65bcf68f70538877662/build.rs.
 
-use std::{cmp::Ordering, fmt};
+use std::{cmp::Ordering, fmt, str::FromStr};
 
 use alfred::{Item, ItemBuilder, Modifier};
+use eyre::eyre;
 use serde::{Deserialize, Serialize};
 use time::{macros::format_description, Date};
 
 const RUST_BLOG_ROOT: &str = "https://blog.rust-lang.org/";
+const RUST_ISSUES_ROOT: &str = "https://github.com/rust-lang/rust/issues/";
+const RUST_MILESTONES_ROOT: &str = "https://github.com/rust-lang/rust/milestone/";
+const RUST_RELEASES_ROOT: &str = "https://github.com/rust-lang/rust/blob/master/RELEASES.md#";
+const RUST_RFCS_ROOT: &str = "https://github.com/rust-lang/rfcs/pull/";
+
+// icon paths are relative to the workflow bundle
+const STABLE_ICON_PATH: &str = "icons/stable.png";
+const UNSTABLE_ICON_PATH: &str = "icons/unstable.png";
+const NIGHTLY_ONLY_ICON_PATH: &str = "icons/nightly-only.png";
+
+/// Maximum number of items listed in a feature's large type text.
+const MAX_LARGE_TYPE_ITEMS: usize = 30;
 
 /// Versions that have been cut are either stable, beta or nightly.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Channel {
+    /// Stable release channel.
     Stable,
+
+    /// Beta channel, becomes the next stable release.
     Beta,
+
+    /// Nightly channel.
     Nightly,
 }
 
@@ -55,6 +73,22 @@ impl fmt::Display for Channel {
     }
 }
 
+impl FromStr for Channel {
+    type Err = eyre::Report;
+
+    /// Parses channel name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "stable" => Ok(Channel::Stable),
+            "beta" => Ok(Channel::Beta),
+            "nightly" => Ok(Channel::Nightly),
+            _ => Err(eyre!(
+                "invalid channel: {s}; expected stable, beta or nightly"
+            )),
+        }
+    }
+}
+
 /// Rust compiler version info.
 #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct CompilerVersionData {
@@ -81,19 +115,50 @@ pub struct CompilerVersionData {
 impl CompilerVersionData {
     /// Creates an Alfred item from version data.
     pub fn to_alfred_item(&self) -> Item<'static> {
+        self.to_alfred_item_builder().into_item()
+    }
+
+    /// Creates a subtitle describing release date and channel, if either is noteworthy.
+    pub fn subtitle(&self) -> Option<String> {
+        // pre-release channels are called out so they stand out from the stable releases
+        let channel_note = match self.channel {
+            Channel::Stable => None,
+            Channel::Beta => Some("beta channel"),
+            Channel::Nightly => Some("nightly channel"),
+        };
+
+        match (self.release_date(), channel_note) {
+            (Some(date), None) => Some(format!("Released {}", format_date(date))),
+            (Some(date), Some(note)) => Some(format!("Released {} · {note}", format_date(date))),
+            (None, Some(note)) => Some(note.to_owned()),
+            (None, None) => None,
+        }
+    }
+
+    /// Creates an Alfred item builder from version data.
+    pub fn to_alfred_item_builder(&self) -> ItemBuilder<'static> {
         let mut builder = ItemBuilder::new(format!("v{} ({})", &self.number, &self.channel));
 
-        if let Some(release_date) = self.release_date() {
-            // August 16 2019
-            let rel_date_str = release_date
-                .format(format_description!("[month repr:long] [day], [year]"))
-                .unwrap();
-            builder.set_subtitle(format!("Released {rel_date_str}"));
+        // stable uid lets Alfred learn which rows are used most
+        builder.set_uid(format!("v{}", self.number));
+
+        // tab completing a version number lists the features stabilized in it
+        builder.set_autocomplete(self.number.clone());
+
+        if let Some(subtitle) = self.subtitle() {
+            builder.set_subtitle(subtitle);
         }
 
-        if let Some(blog_post) = self.blog_post_path.as_deref() {
-            let blog_post_url = format!("{}{}", RUST_BLOG_ROOT, blog_post.to_owned());
+        // primary action opens the most useful page available for the release
+        if let Some(url) = self
+            .release_notes_url()
+            .or_else(|| self.blog_post_url())
+            .or_else(|| self.milestone_url())
+        {
+            builder.set_arg(url);
+        }
 
+        if let Some(blog_post_url) = self.blog_post_url() {
             builder.set_quicklook_url(blog_post_url.clone());
 
             builder.set_modifier(
@@ -105,37 +170,123 @@ impl CompilerVersionData {
             );
         };
 
-        builder.into_item()
+        if let Some(milestone_url) = self.milestone_url() {
+            builder.set_modifier(
+                Modifier::Command,
+                Some("Press enter to open GitHub milestone."),
+                Some(milestone_url),
+                true,
+                None,
+            );
+        } else {
+            builder.set_modifier(
+                Modifier::Command,
+                Some("No GitHub milestone."),
+                None::<String>,
+                false,
+                None,
+            );
+        }
+
+        if let Some(release_notes_url) = self.release_notes_url() {
+            builder.set_modifier(
+                Modifier::Control,
+                Some("Press enter to open release notes."),
+                Some(release_notes_url),
+                true,
+                None,
+            );
+        } else {
+            builder.set_modifier(
+                Modifier::Control,
+                Some("No release notes."),
+                None::<String>,
+                false,
+                None,
+            );
+        }
+
+        builder
     }
 }
 
 impl CompilerVersionData {
-    fn release_date(&self) -> Option<Date> {
+    /// Parses version number into numeric (major, minor, patch) components.
+    ///
+    /// Returns `None` if the version number is malformed.
+    pub fn version_tuple(&self) -> Option<(u64, u64, u64)> {
+        parse_version(&self.number)
+    }
+
+    fn release_notes_url(&self) -> Option<String> {
+        self.release_notes
+            .as_deref()
+            .map(|anchor| format!("{RUST_RELEASES_ROOT}{anchor}"))
+    }
+
+    fn blog_post_url(&self) -> Option<String> {
+        self.blog_post_path
+            .as_deref()
+            .map(|path| format!("{RUST_BLOG_ROOT}{path}"))
+    }
+
+    fn milestone_url(&self) -> Option<String> {
+        self.gh_milestone_id
+            .map(|id| format!("{RUST_MILESTONES_ROOT}{id}"))
+    }
+
+    /// Parses release date, expected in "yyyy-mm-dd" format, e.g. "2019-08-16".
+    ///
+    /// Returns `None` if the release date is unknown or malformed.
+    pub fn release_date(&self) -> Option<Date> {
         self.release_date.as_deref().and_then(|date| {
             Date::parse(date, format_description!("[year repr:full]-[month]-[day]")).ok()
         })
     }
 }
 
-impl PartialOrd<CompilerVersionData> for CompilerVersionData {
-    fn partial_cmp(&self, other: &CompilerVersionData) -> Option<Ordering> {
-        self.channel
-            .cmp(&other.channel)
-            .then_with(|| {
-                let self_rel = match self.release_date() {
-                    Some(rel) => rel,
-                    None => return Ordering::Equal,
-                };
+impl PartialOrd for CompilerVersionData {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-                let other_rel = match other.release_date() {
-                    Some(rel) => rel,
-                    None => return Ordering::Equal,
-                };
+impl Ord for CompilerVersionData {
+    /// Orders versions by version number, then release date, then channel.
+    ///
+    /// Version numbers come first so that versions with unknown release dates, e.g. upcoming
+    /// betas and nightlies, still sort among the others. Comparing lexicographically keeps the
+    /// ordering total, which sorting relies on.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.version_tuple()
+            .cmp(&other.version_tuple())
+            .then_with(|| self.release_date().cmp(&other.release_date()))
+            .then_with(|| self.channel.cmp(&other.channel))
+    }
+}
+
+/// Parses version number into numeric (major, minor, patch) components.
+///
+/// Missing minor or patch components default to 0, e.g. "1.65" parses to `(1, 65, 0)`.
+pub(crate) fn parse_version(number: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = number.trim().split('.');
+
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().map_or(Ok(0), str::parse).ok()?;
+    let patch = parts.next().map_or(Ok(0), str::parse).ok()?;
 
-                self_rel.cmp(&other_rel)
-            })
-            .into()
+    // more than three components is not a valid version
+    if parts.next().is_some() {
+        return None;
     }
+
+    Some((major, minor, patch))
+}
+
+/// Formats date for display, e.g. "August 16, 2019".
+fn format_date(date: Date) -> String {
+    date.format(format_description!("[month repr:long] [day], [year]"))
+        .unwrap()
 }
 
 /// Rust "feature" info for some arbitrary definition of feature.
@@ -196,22 +347,148 @@ pub struct FeatureData {
 
 impl FeatureData {
     /// Creates an Alfred row item from feature data.
-    pub fn to_alfred_item(&self, base_url: &str) -> Item<'static> {
-        let mut builder = ItemBuilder::new(self.title.clone());
+    ///
+    /// When provided, the stabilization version data is used to show the release date.
+    pub fn to_alfred_item(
+        &self,
+        base_url: &str,
+        version: Option<&CompilerVersionData>,
+    ) -> Item<'static> {
+        self.to_alfred_item_builder(base_url, version).into_item()
+    }
+
+    /// Returns short summary of the feature's stabilization status, e.g. "since v1.65" or
+    /// "nightly only · try_blocks".
+    ///
+    /// When provided, the stabilization version data is used to include the release date.
+    pub fn subtitle(&self, version: Option<&CompilerVersionData>) -> String {
+        let release_date = version.and_then(|version| version.release_date());
+
+        let mut subtitle = match (self.version_number.as_deref(), release_date) {
+            (Some(v), Some(date)) => format!("since v{v} ({})", format_date(date)),
+            (Some(v), None) => format!("since v{v}"),
+
+            // show flag for unstable features since it's needed to use them
+            (None, _) => {
+                // features without docs are usually only usable, and documented, on nightly
+                let nightly_only = self.doc_path.is_none();
+                let status = if nightly_only {
+                    "nightly only"
+                } else {
+                    "unstable"
+                };
+
+                let mut subtitle = match self.flag.as_deref() {
+                    Some(flag) => format!("{status} · {flag}"),
+                    None => status.to_owned(),
+                };
 
-        match self.version_number.as_deref() {
-            Some(v) => builder.set_subtitle(format!("since v{v}")),
-            None => builder.set_subtitle("unstable"),
+                if nightly_only && self.unstable_book_path.is_some() {
+                    subtitle.push_str(" · see Unstable Book");
+                }
+
+                subtitle
+            }
+        };
+
+        // edition-gated features give context for edition migrations
+        if self.edition_guide_path.is_some() {
+            subtitle.push_str(" · edition");
+        }
+
+        subtitle
+    }
+
+    /// Returns best-effort std docs URL for features that consist of a single standard library
+    /// item, e.g. `core::array::from_fn`.
+    ///
+    /// The item's kind, which docs page paths depend on, is unknown so a search for the item is
+    /// linked instead, which resolves reliably.
+    fn std_docs_url(&self) -> Option<String> {
+        let [item] = self.items.as_slice() else {
+            return None;
         };
 
-        builder.set_arg(format!("{}/features/{}", base_url, &self.slug));
-        builder.set_quicklook_url(format!("{}/features/{}", base_url, &self.slug));
+        let is_std_path = ["std::", "core::", "alloc::"]
+            .iter()
+            .any(|krate| item.starts_with(krate))
+            && item.split("::").all(|segment| {
+                !segment.is_empty()
+                    && segment
+                        .chars()
+                        .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+            });
+
+        is_std_path.then(|| format!("https://doc.rust-lang.org/std/?search={item}"))
+    }
+
+    /// Returns language items, one per line, for display in large type.
+    ///
+    /// Items are sorted and, past a limit, truncated with a line noting how many were left out.
+    /// When given, the item at index `first` is listed first.
+    pub fn items_text(&self, first: Option<usize>) -> String {
+        let first = first.and_then(|idx| self.items.get(idx));
+
+        let mut items = self
+            .items
+            .iter()
+            .filter(|&item| Some(item) != first)
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        items.sort_unstable();
+
+        if let Some(first) = first {
+            items.insert(0, first);
+        }
+
+        let more = items.len().saturating_sub(MAX_LARGE_TYPE_ITEMS);
+        items.truncate(MAX_LARGE_TYPE_ITEMS);
+
+        let mut text = items.join("\n");
+
+        if more > 0 {
+            text.push_str(&format!("\n…and {more} more"));
+        }
+
+        text
+    }
+
+    /// Creates an Alfred row item builder from feature data.
+    ///
+    /// Same as [`to_alfred_item`](Self::to_alfred_item) but allows callers to customize the row.
+    pub fn to_alfred_item_builder(
+        &self,
+        base_url: &str,
+        version: Option<&CompilerVersionData>,
+    ) -> ItemBuilder<'static> {
+        let mut builder = ItemBuilder::new(self.title.clone());
+
+        builder.set_subtitle(self.subtitle(version));
+        builder.set_uid(self.slug.clone());
+        builder.set_autocomplete(self.slug.clone());
+
+        if self.version_number.is_some() {
+            builder.set_icon_path(STABLE_ICON_PATH);
+        } else if self.doc_path.is_none() {
+            // without docs there is nothing usable on stable, so these are flagged more strongly
+            builder.set_icon_path(NIGHTLY_ONLY_ICON_PATH);
+        } else {
+            builder.set_icon_path(UNSTABLE_ICON_PATH);
+        }
+
+        let feature_url = format!("{}/features/{}", base_url, &self.slug);
+
+        builder.set_arg(feature_url.clone());
+        builder.set_quicklook_url(feature_url.clone());
+
+        // all modifier keys are taken so the link is copied with Alfred's copy shortcut instead
+        builder.set_text_copy(feature_url);
 
         if self.items.is_empty() {
             // seems to prevent large type activation
             builder.set_text_large_type(" ".to_owned());
         } else {
-            builder.set_text_large_type(self.items.join("\n"));
+            builder.set_text_large_type(self.items_text(None));
         }
 
         if let Some(ref doc_path) = self.doc_path {
@@ -225,6 +502,25 @@ impl FeatureData {
                 true,
                 None,
             );
+        } else if let Some(ref unstable_book_path) = self.unstable_book_path {
+            // unstable features rarely have regular docs so the unstable book takes their place
+            builder.set_modifier(
+                Modifier::Option,
+                Some("Press enter to open Unstable Book."),
+                Some(format!(
+                    "https://doc.rust-lang.org/unstable-book/{unstable_book_path}"
+                )),
+                true,
+                None,
+            );
+        } else if let Some(std_docs_url) = self.std_docs_url() {
+            builder.set_modifier(
+                Modifier::Option,
+                Some("Press enter to search std docs."),
+                Some(std_docs_url),
+                true,
+                None,
+            );
         } else {
             builder.set_modifier(
                 Modifier::Option,
@@ -235,6 +531,105 @@ impl FeatureData {
             );
         }
 
-        builder.into_item()
+        if let Some(ref flag) = self.flag {
+            builder.set_modifier(
+                Modifier::Command,
+                Some("Press enter to copy feature flag."),
+                Some(format!("#![feature({flag})]")),
+                true,
+                None,
+            );
+        } else {
+            builder.set_modifier(
+                Modifier::Command,
+                Some("No feature flag."),
+                None::<String>,
+                false,
+                None,
+            );
+        }
+
+        if let Some(issue_id) = self.tracking_issue_id {
+            builder.set_modifier(
+                Modifier::Control,
+                Some(format!("Press enter to open tracking issue #{issue_id}.")),
+                Some(format!("{RUST_ISSUES_ROOT}{issue_id}")),
+                true,
+                None,
+            );
+        } else {
+            builder.set_modifier(
+                Modifier::Control,
+                Some("No tracking issue."),
+                None::<String>,
+                false,
+                None,
+            );
+        }
+
+        if let Some(rfc_id) = self.rfc_id {
+            builder.set_modifier(
+                Modifier::Shift,
+                Some(format!("Press enter to open RFC #{rfc_id}.")),
+                Some(format!("{RUST_RFCS_ROOT}{rfc_id}")),
+                true,
+                None,
+            );
+        } else {
+            builder.set_modifier(
+                Modifier::Shift,
+                Some("No RFC."),
+                None::<String>,
+                false,
+                None,
+            );
+        }
+
+        if let Some(ref edition_guide_path) = self.edition_guide_path {
+            builder.set_modifier(
+                Modifier::Fn,
+                Some("Press enter to open edition guide."),
+                Some(format!(
+                    "https://doc.rust-lang.org/edition-guide/{edition_guide_path}"
+                )),
+                true,
+                None,
+            );
+        } else {
+            builder.set_modifier(
+                Modifier::Fn,
+                Some("No edition guide entry."),
+                None::<String>,
+                false,
+                None,
+            );
+        }
+
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edition_feature_subtitle() {
+        let feature = FeatureData {
+            title: "Rust 2021 prelude".to_owned(),
+            version_number: Some("1.56".to_owned()),
+            edition_guide_path: Some("rust-2021/prelude.html".to_owned()),
+            ..FeatureData::default()
+        };
+
+        assert_eq!(feature.subtitle(None), "since v1.56 · edition");
+
+        let version = CompilerVersionData {
+            number: "1.56".to_owned(),
+            release_date: Some("2021-10-21".to_owned()),
+            ..CompilerVersionData::default()
+        };
+
+        assert!(feature.subtitle(Some(&version)).ends_with(") · edition"));
     }
 }
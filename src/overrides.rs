@@ -0,0 +1,41 @@
+//! User-provided feature database overrides.
+
+use std::{fs, io, path::PathBuf};
+
+use eyre::WrapErr as _;
+
+use crate::Db;
+
+/// Loads optional overrides file from the config directory.
+///
+/// Overrides use the same format as caniuse.rs's `features.json` but may leave out either the
+/// features or versions. Returns `None` if no overrides file exists.
+pub fn overrides_fetch() -> eyre::Result<Option<Db>> {
+    let Some(path) = overrides_path() else {
+        return Ok(None);
+    };
+
+    let json = match fs::read(&path) {
+        Ok(json) => json,
+
+        // overrides are optional
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+
+        Err(err) => return Err(err.into()),
+    };
+
+    let db = Db::from_reader(&json[..])
+        .wrap_err_with(|| format!("invalid overrides file {}", path.display()))?;
+
+    Ok(Some(db))
+}
+
+/// Returns absolute path to location of overrides file, if config directory is known.
+fn overrides_path() -> Option<PathBuf> {
+    let config_dir = dirs::config_dir()?;
+    Some(
+        config_dir
+            .join("dev.robjtede.alfred-caniuse-rs")
+            .join("overrides.json"),
+    )
+}
@@ -0,0 +1,456 @@
+//! Feature search index and relevance scoring.
+
+use std::{env, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::FeatureData;
+
+// lookup score bases by match kind; similarity is added as a fractional tiebreaker
+const SCORE_EXACT: f64 = 4.0;
+const SCORE_PREFIX: f64 = 3.0;
+const SCORE_SUBSTRING: f64 = 2.0;
+const SCORE_DOC_PATH: f64 = 1.0;
+const SCORE_FUZZY: f64 = 0.0;
+
+/// Scale applied to similarities so that, added to a score base, they never reach the next base.
+const SIMILARITY_SCALE: f64 = 0.99;
+
+/// Default minimum similarity of query to slug or flag for a fuzzy match.
+const DEFAULT_FUZZY_SLUG_THRESHOLD: f64 = 0.65;
+
+/// Default minimum similarity of query to title or an alias for a fuzzy match.
+const DEFAULT_FUZZY_TITLE_THRESHOLD: f64 = 0.4;
+
+/// Minimum similarities required for fuzzy matches.
+#[derive(Debug, Clone, Copy)]
+struct FuzzyThresholds {
+    slug: f64,
+    title: f64,
+}
+
+impl FuzzyThresholds {
+    /// Returns thresholds, read once from `CANIUSE_FUZZY_SLUG_THRESHOLD` and
+    /// `CANIUSE_FUZZY_TITLE_THRESHOLD` if set.
+    fn get() -> Self {
+        static THRESHOLDS: OnceLock<FuzzyThresholds> = OnceLock::new();
+
+        *THRESHOLDS.get_or_init(|| FuzzyThresholds {
+            slug: threshold_from_env("CANIUSE_FUZZY_SLUG_THRESHOLD", DEFAULT_FUZZY_SLUG_THRESHOLD),
+            title: threshold_from_env(
+                "CANIUSE_FUZZY_TITLE_THRESHOLD",
+                DEFAULT_FUZZY_TITLE_THRESHOLD,
+            ),
+        })
+    }
+}
+
+/// Reads threshold from environment variable, clamped to `0.0..=1.0`.
+fn threshold_from_env(key: &str, default: f64) -> f64 {
+    env::var(key)
+        .ok()
+        .and_then(|val| val.trim().parse::<f64>().ok())
+        .filter(|val| !val.is_nan())
+        .map_or(default, |val| val.clamp(0.0, 1.0))
+}
+
+/// Normalizes text for matching by lowercasing it and stripping accents from Latin letters.
+///
+/// For example, "Naïve" normalizes to "naive".
+pub(crate) fn normalize(text: &str) -> String {
+    text.to_lowercase().chars().map(fold_accent).collect()
+}
+
+/// Converts documentation path into item path, e.g. "std/mem/fn.swap.html" into "std::mem::swap".
+///
+/// Module index pages resolve to the module, e.g. "std/mem/index.html" into "std::mem".
+pub(crate) fn doc_item_path(doc_path: &str) -> String {
+    // fragments and extensions are not part of the item path
+    let doc_path = doc_path.split('#').next().unwrap_or_default();
+    let doc_path = doc_path.strip_suffix(".html").unwrap_or(doc_path);
+
+    doc_path
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != "index")
+        // item pages are prefixed with their kind, e.g. "fn." or "struct."
+        .map(|segment| segment.split_once('.').map_or(segment, |(_, name)| name))
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// Maps accented Latin letter to its unaccented form, leaving other characters unchanged.
+///
+/// Only covers lowercase letters of the Latin-1 Supplement, which is enough for typical queries.
+fn fold_accent(ch: char) -> char {
+    match ch {
+        'à'..='å' => 'a',
+        'ç' => 'c',
+        'è'..='ë' => 'e',
+        'ì'..='ï' => 'i',
+        'ñ' => 'n',
+        'ò'..='ö' | 'ø' => 'o',
+        'ù'..='ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        _ => ch,
+    }
+}
+
+/// Which searchable field of a feature matched a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    /// Slug contains the query.
+    Slug,
+
+    /// Feature flag contains the query.
+    Flag,
+
+    /// Title contains the query.
+    Title,
+
+    /// Item at the given index of [`FeatureData::items`] contains the query.
+    Item(usize),
+
+    /// Alias at the given index of [`FeatureData::aliases`] contains the query.
+    Alias(usize),
+
+    /// Item path of [`FeatureData::doc_path`], e.g. "std::mem::swap", contains the query.
+    DocPath,
+
+    /// No field contains the query but the feature is similar enough to it.
+    Fuzzy,
+}
+
+impl MatchKind {
+    /// Describes which field matched, e.g. "matched alias: try_blocks".
+    ///
+    /// Returns `None` when the reason for the match is evident from the feature's title.
+    pub fn describe(self, feature: &FeatureData) -> Option<String> {
+        match self {
+            MatchKind::Slug | MatchKind::Title => None,
+            MatchKind::Flag => feature
+                .flag
+                .as_deref()
+                .map(|flag| format!("matched flag: {flag}")),
+            MatchKind::Item(idx) => feature
+                .items
+                .get(idx)
+                .map(|item| format!("matched item: {item}")),
+            MatchKind::Alias(idx) => feature
+                .aliases
+                .get(idx)
+                .map(|alias| format!("matched alias: {alias}")),
+            MatchKind::DocPath => feature
+                .doc_path
+                .as_deref()
+                .map(|doc_path| format!("matched docs: {}", doc_item_path(doc_path))),
+            MatchKind::Fuzzy => Some("similar match".to_owned()),
+        }
+    }
+}
+
+/// Searchable fields of a feature, normalized ahead of time.
+///
+/// Normalization is done once per feature instead of once per feature per query. The index is
+/// cached alongside the database so it is not rebuilt on every launch.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct SearchEntry {
+    /// Key of the feature in the database's feature map.
+    key: String,
+    slug: String,
+    flag: Option<String>,
+    title: String,
+    items: Vec<String>,
+    aliases: Vec<String>,
+    doc_path: Option<String>,
+}
+
+impl SearchEntry {
+    /// Creates search entry for the feature stored under `key`.
+    pub(crate) fn new(key: &str, feature: &FeatureData) -> Self {
+        Self {
+            key: key.to_owned(),
+            slug: normalize(&feature.slug),
+            flag: feature.flag.as_deref().map(normalize),
+            title: normalize(&feature.title),
+            items: feature.items.iter().map(|item| normalize(item)).collect(),
+            aliases: feature
+                .aliases
+                .iter()
+                .map(|alias| normalize(alias))
+                .collect(),
+            doc_path: feature
+                .doc_path
+                .as_deref()
+                .map(|doc_path| normalize(&doc_item_path(doc_path))),
+        }
+    }
+
+    /// Returns key of the feature in the database's feature map.
+    pub(crate) fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Scores how well the feature matches the query, returning `None` if it does not match.
+    ///
+    /// The query is expected to be [normalized](normalize). The integer part of the score denotes
+    /// the kind of match (exact slug or item path, prefix, substring or fuzzy) and the fractional
+    /// part is the scaled Sorensen–Dice similarity, used as a tiebreaker. The field that matched is
+    /// also returned.
+    ///
+    /// Multi-word queries that do not match as a whole phrase match when every word is found in
+    /// some field, e.g. "const generic" matches "const generics". Otherwise, they can still match
+    /// fuzzily as a whole phrase, e.g. "cnst generics".
+    pub(crate) fn score(&self, query: &str) -> Option<(f64, MatchKind)> {
+        let tokens = query.split_whitespace().collect::<Vec<_>>();
+
+        if tokens.len() <= 1 {
+            return self.score_term(query);
+        }
+
+        // matching as a whole phrase takes precedence
+        let phrase_match = self.score_term(query);
+
+        if let Some((score, kind)) = phrase_match {
+            if kind != MatchKind::Fuzzy {
+                return Some((score, kind));
+            }
+        }
+
+        let kinds = tokens
+            .iter()
+            .map(|token| self.substring_match(token))
+            .collect::<Option<Vec<_>>>();
+
+        match kinds {
+            Some(kinds) => {
+                // words only found in the doc path make for a weaker match overall
+                let base = if kinds.contains(&MatchKind::DocPath) {
+                    SCORE_DOC_PATH
+                } else {
+                    SCORE_SUBSTRING
+                };
+
+                Some((base + self.similarity(query), kinds[0]))
+            }
+
+            // typos in any word are still caught by fuzzy matching the whole phrase
+            None => phrase_match,
+        }
+    }
+
+    /// Scores query as a single search term.
+    fn score_term(&self, query: &str) -> Option<(f64, MatchKind)> {
+        let slug = self.slug.as_str();
+        let flag = self.flag.as_deref();
+        let title = self.title.as_str();
+
+        let similarity = self.similarity(query);
+
+        if slug == query {
+            return Some((SCORE_EXACT + similarity, MatchKind::Slug));
+        }
+
+        // language item paths, e.g. "option::zip", are specific enough to rank alongside slugs
+        if let Some(idx) = self.item_path_match(query) {
+            return Some((SCORE_EXACT + similarity, MatchKind::Item(idx)));
+        }
+
+        // prefix matches rank above other substring matches, which matters most for short queries
+        if slug.starts_with(query) {
+            return Some((SCORE_PREFIX + similarity, MatchKind::Slug));
+        }
+
+        if title.starts_with(query) {
+            return Some((SCORE_PREFIX + similarity, MatchKind::Title));
+        }
+
+        if flag.is_some_and(|flag| flag.starts_with(query)) {
+            return Some((SCORE_PREFIX + similarity, MatchKind::Flag));
+        }
+
+        if let Some(kind) = self.substring_match(query) {
+            // doc paths rank below other fields since their item is not always what the feature
+            // is known for
+            let base = if kind == MatchKind::DocPath {
+                SCORE_DOC_PATH
+            } else {
+                SCORE_SUBSTRING
+            };
+
+            return Some((base + similarity, kind));
+        }
+
+        let thresholds = FuzzyThresholds::get();
+
+        let fuzzy_match = strsim::sorensen_dice(query, slug) > thresholds.slug
+            || flag.is_some_and(|flag| strsim::sorensen_dice(query, flag) > thresholds.slug)
+            || self.name_similarity(query) > thresholds.title;
+
+        if fuzzy_match {
+            return Some((SCORE_FUZZY + similarity, MatchKind::Fuzzy));
+        }
+
+        None
+    }
+
+    /// Returns the best Sorensen–Dice similarity of the query to the slug or name, scaled into
+    /// `0.0..1.0` so it only breaks ties between matches of the same kind.
+    fn similarity(&self, query: &str) -> f64 {
+        let similarity = strsim::sorensen_dice(query, &self.slug).max(self.name_similarity(query));
+        similarity * SIMILARITY_SCALE
+    }
+
+    /// Returns the best Sorensen–Dice similarity of the query to the title or any alias.
+    ///
+    /// Aliases are scored as part of the name so features better known by an alias are not
+    /// outranked by weaker title matches on other features.
+    fn name_similarity(&self, query: &str) -> f64 {
+        self.aliases
+            .iter()
+            .map(|alias| strsim::sorensen_dice(query, alias))
+            .fold(strsim::sorensen_dice(query, &self.title), f64::max)
+    }
+
+    /// Returns index of first item that is the query or, for path queries, ends with it as a path,
+    /// e.g. "option::zip" matches "core::option::option::zip".
+    fn item_path_match(&self, query: &str) -> Option<usize> {
+        let is_path = query.contains("::");
+
+        self.items.iter().position(|item| {
+            item.strip_suffix(query)
+                .is_some_and(|rest| rest.is_empty() || (is_path && rest.ends_with("::")))
+        })
+    }
+
+    /// Returns first field, in order of precedence, that contains the query.
+    fn substring_match(&self, query: &str) -> Option<MatchKind> {
+        if self.slug.contains(query) {
+            return Some(MatchKind::Slug);
+        }
+
+        if self
+            .flag
+            .as_deref()
+            .is_some_and(|flag| flag.contains(query))
+        {
+            return Some(MatchKind::Flag);
+        }
+
+        if self.title.contains(query) {
+            return Some(MatchKind::Title);
+        }
+
+        if let Some(idx) = self.items.iter().position(|item| item.contains(query)) {
+            return Some(MatchKind::Item(idx));
+        }
+
+        if let Some(idx) = self.aliases.iter().position(|alias| alias.contains(query)) {
+            return Some(MatchKind::Alias(idx));
+        }
+
+        // checked last so that matches on other fields take precedence
+        if self
+            .doc_path
+            .as_deref()
+            .is_some_and(|doc_path| doc_path.contains(query))
+        {
+            return Some(MatchKind::DocPath);
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(slug: &str, title: &str, items: &[&str]) -> SearchEntry {
+        let feature = FeatureData {
+            slug: slug.to_owned(),
+            title: title.to_owned(),
+            items: items.iter().map(|&item| item.to_owned()).collect(),
+            ..FeatureData::default()
+        };
+
+        SearchEntry::new(slug, &feature)
+    }
+
+    #[test]
+    fn multi_word_queries_match_all_words() {
+        let min_const_generics = entry("min_const_generics", "const generics MVP", &[]);
+        let impl_trait = entry("impl_trait_in_arg", "impl Trait in argument position", &[]);
+        let let_else = entry("let_else", "let-else statements", &[]);
+
+        assert!(min_const_generics.score("const generic").is_some());
+        assert!(impl_trait.score("impl trait").is_some());
+        assert!(let_else.score("let else").is_some());
+
+        // every word must match
+        assert!(min_const_generics.score("const trait").is_none());
+        assert!(let_else.score("let chains").is_none());
+    }
+
+    #[test]
+    fn typoed_multi_word_queries_match_fuzzily() {
+        let min_const_generics = entry("min_const_generics", "const generics MVP", &[]);
+
+        for query in ["cnst generics", "const generix"] {
+            assert_eq!(
+                min_const_generics.score(query).map(|(_, kind)| kind),
+                Some(MatchKind::Fuzzy),
+                "{query}",
+            );
+        }
+    }
+
+    #[test]
+    fn doc_paths_convert_to_item_paths() {
+        assert_eq!(doc_item_path("std/mem/fn.swap.html"), "std::mem::swap");
+        assert_eq!(doc_item_path("std/mem/index.html"), "std::mem");
+        assert_eq!(
+            doc_item_path("std/option/enum.Option.html#method.zip"),
+            "std::option::Option",
+        );
+    }
+
+    #[test]
+    fn doc_path_queries_match() {
+        let feature = FeatureData {
+            slug: "mem_take".to_owned(),
+            title: "mem::take".to_owned(),
+            doc_path: Some("std/mem/fn.take.html".to_owned()),
+            ..FeatureData::default()
+        };
+        let entry = SearchEntry::new("mem_take", &feature);
+
+        assert_eq!(
+            entry.score("std::mem::t").map(|(_, kind)| kind),
+            Some(MatchKind::DocPath),
+        );
+    }
+
+    #[test]
+    fn doc_path_matches_rank_below_other_substring_matches() {
+        let doc_path_match = SearchEntry::new(
+            "mem_swp",
+            &FeatureData {
+                slug: "mem_swp".to_owned(),
+                title: "swp".to_owned(),
+                doc_path: Some("std/mem/fn.swap.html".to_owned()),
+                ..FeatureData::default()
+            },
+        );
+        let title_match = entry("cell_methods", "Cell::replace and Cell::swap methods", &[]);
+
+        let (doc_path_score, kind) = doc_path_match.score("swap").unwrap();
+        assert_eq!(kind, MatchKind::DocPath);
+
+        let (title_score, kind) = title_match.score("swap").unwrap();
+        assert_eq!(kind, MatchKind::Title);
+
+        // doc path hit is more similar to the query but still ranks lower
+        assert!(doc_path_match.similarity("swap") > title_match.similarity("swap"));
+        assert!(title_score > doc_path_score);
+    }
+}
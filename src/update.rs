@@ -1,20 +1,21 @@
 //! Self-update checks.
 
-use std::{
-    fs,
-    io::{self, Write as _},
-};
+use std::{env, fs, io};
 
 use eyre::eyre;
 use serde::{Deserialize, Serialize};
 
-use crate::cache::cache_dir;
+use crate::{
+    cache::{cache_dir, write_atomic},
+    http,
+    models::parse_version,
+};
 
 const DAY_IN_SECS: u64 = 3600 * 24;
 const LATEST_URL: &str = "https://github.com/robjtede/alfred-caniuse-rs/releases";
 const LATEST_ZIP_PATH: &str = "/latest/download/package.zip";
 const SELF_VERSION: &str = env!("CARGO_PKG_VERSION");
-const UPDATE_CHECK_FILENAME: &str = "update-check.json";
+pub(crate) const UPDATE_CHECK_FILENAME: &str = "update-check.json";
 
 /// Returning None means no action to take.
 pub fn self_update_check_item() -> Option<alfred::Item<'static>> {
@@ -26,16 +27,34 @@ pub fn self_update_check_item() -> Option<alfred::Item<'static>> {
     })
 }
 
+/// Returns row showing the workflow's version and whether an update is available.
+pub fn self_version_item() -> alfred::Item<'static> {
+    let builder = alfred::ItemBuilder::new(format!("alfred-caniuse-rs v{SELF_VERSION}"));
+
+    match self_update_check() {
+        Some(url) => builder
+            .subtitle("An update is available. Press enter to go to download page.")
+            .arg(url),
+
+        None => builder.subtitle("No update available.").valid(false),
+    }
+    .into_item()
+}
+
 /// Returning None means no action to take.
 fn self_update_check() -> Option<&'static str> {
+    if http::offline_mode() {
+        return None;
+    }
+
     match self_need_update_check() {
         // fall through to update check
         Ok(NeedsCheck::Yes) => {
-            eprintln!("update check will be perform")
+            log::debug!("update check will be performed")
         }
 
         Ok(NeedsCheck::No) => {
-            eprintln!("skip update check");
+            log::debug!("skip update check");
             return None;
         }
 
@@ -46,8 +65,8 @@ fn self_update_check() -> Option<&'static str> {
         Err(err) => {
             let check_file = cache_dir().join(UPDATE_CHECK_FILENAME);
 
-            eprintln!("update check cache failed: {err}");
-            eprintln!("deleting update check file from: {check_file:?}");
+            log::warn!("update check cache failed: {err}");
+            log::debug!("deleting update check file from: {check_file:?}");
 
             // attempt to clean up any potentially corrupted cache state
             let _ = fs::remove_file(check_file);
@@ -60,10 +79,10 @@ fn self_update_check() -> Option<&'static str> {
     match self_update_check_inner() {
         Ok(true) => return Some(LATEST_URL),
         Ok(false) => {
-            eprintln!("no update available");
+            log::debug!("no update available");
         }
         Err(err) => {
-            eprintln!("error fetching update: {err}");
+            log::warn!("error fetching update: {err}");
         }
     }
 
@@ -106,7 +125,7 @@ impl UpdateCheck {
         let last_check_delta = time::OffsetDateTime::now_utc() - self.last_check;
 
         // only thing to check is whether check has occurred recently
-        if last_check_delta > time::Duration::seconds(DAY_IN_SECS as i64) {
+        if last_check_delta > update_interval() {
             NeedsCheck::Yes
         } else {
             NeedsCheck::No
@@ -114,6 +133,18 @@ impl UpdateCheck {
     }
 }
 
+/// Returns minimum time between update checks, read from `CANIUSE_UPDATE_INTERVAL_SECS` if set.
+///
+/// Defaults to one day when unset or invalid.
+fn update_interval() -> time::Duration {
+    let secs = env::var("CANIUSE_UPDATE_INTERVAL_SECS")
+        .ok()
+        .and_then(|secs| secs.trim().parse::<u64>().ok())
+        .unwrap_or(DAY_IN_SECS);
+
+    time::Duration::seconds(secs.try_into().unwrap_or(i64::MAX))
+}
+
 // Returning errors to signal a clean up of the cache file may be necessary.
 fn self_need_update_check() -> eyre::Result<NeedsCheck> {
     let update_check_cache_path = cache_dir().join(UPDATE_CHECK_FILENAME);
@@ -130,30 +161,41 @@ fn self_need_update_check() -> eyre::Result<NeedsCheck> {
     Ok(update_check.remote_check_needed())
 }
 
+/// Finds version in download URL, e.g. `(1, 2, 3)` in "update-server.com/release/v1.2.3/download".
+fn url_version(url: &str) -> Option<(u64, u64, u64)> {
+    url.split('/')
+        .map(|segment| segment.strip_prefix('v').unwrap_or(segment))
+        // require a dot so that numeric IDs in the URL are not mistaken for versions
+        .filter(|segment| segment.contains('.'))
+        .find_map(parse_version)
+}
+
 // Makes API call to GitHub to check latest
 fn self_update_check_inner() -> eyre::Result<bool> {
-    let client = ureq::builder()
+    let url = [LATEST_URL, LATEST_ZIP_PATH].concat();
+
+    let client = http::agent_builder(&url)
         .redirects(0)
         .timeout(std::time::Duration::from_secs(1))
         .build();
 
-    let url = [LATEST_URL, LATEST_ZIP_PATH].concat();
     let res = client.get(&url).call()?;
     let latest_url = res
         .header("location")
         .ok_or_else(|| eyre!("no location header in update check response"))?;
 
+    let latest_version = url_version(latest_url)
+        .ok_or_else(|| eyre!("no version found in update check location: {latest_url}"))?;
+    let self_version =
+        parse_version(SELF_VERSION).ok_or_else(|| eyre!("invalid self version: {SELF_VERSION}"))?;
+
+    // only flag update when remote is strictly newer
+    let update_needed = latest_version > self_version;
+
     // ensure containing direction of cache file exists
     fs::create_dir_all(cache_dir())?;
 
     let update_check_cache_path = cache_dir().join(UPDATE_CHECK_FILENAME);
-    let mut file = fs::File::create(&update_check_cache_path)?;
-
-    // for some download URL like:
-    // update-server.com/release/v1.2.3/download
-    // it should only be required that the current version exists somewhere in that URL
-    // to be considered the latest to avoid needing regex and oddities with v* prefixes
-    let update_needed = !latest_url.contains(SELF_VERSION);
 
     let last_check = UpdateCheck {
         update_needed,
@@ -162,10 +204,11 @@ fn self_update_check_inner() -> eyre::Result<bool> {
     };
 
     let update_check = serde_json::to_vec_pretty(&last_check)?;
-    // TODO: less resilient than other file ops
-    file.write_all(&update_check)?;
 
-    eprintln!("checking cache at {:?}", &update_check_cache_path);
+    // interrupted writes leave any existing check file intact instead of truncating it
+    write_atomic(&update_check_cache_path, &update_check)?;
+
+    log::debug!("checking cache at {:?}", &update_check_cache_path);
 
     Ok(update_needed)
 }
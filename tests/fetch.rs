@@ -0,0 +1,126 @@
+//! Fetching and caching the feature database from a local HTTP server.
+
+use std::{env, fs, process, thread};
+
+use alfred_caniuse_rs::{cache_fetch, cache_put, Cached, Db};
+use tiny_http::{Header, Response, Server};
+
+const ETAG: &str = "\"features-v1\"";
+
+const FEATURES_JSON: &str = r#"{
+    "versions": {
+        "1.65": {
+            "number": "1.65",
+            "channel": "stable",
+            "release_date": "2022-11-03",
+            "blog_post_path": "2022/11/03/Rust-1.65.0.html",
+            "gh_milestone_id": 100
+        }
+    },
+    "features": {
+        "let_else": {
+            "title": "let-else statements",
+            "flag": "let_else",
+            "rfc_id": 3137,
+            "tracking_issue_id": 87335,
+            "items": ["let-else"],
+            "version": "1.65"
+        },
+        "try_blocks": {
+            "title": "try blocks",
+            "flag": "try_blocks",
+            "rfc_id": 243
+        }
+    }
+}"#;
+
+/// Serves `FEATURES_JSON` with an `ETag`, answering matching conditional requests with 304.
+///
+/// Returns base URL of the server, which runs until the test process exits.
+fn serve() -> String {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let port = server.server_addr().to_ip().unwrap().port();
+
+    thread::spawn(move || {
+        for req in server.incoming_requests() {
+            let not_modified = req
+                .headers()
+                .iter()
+                .any(|header| header.field.equiv("if-none-match") && header.value == ETAG);
+
+            let res = if req.url() != "/features.json" {
+                Response::from_string("not found").with_status_code(404)
+            } else if not_modified {
+                Response::from_string("").with_status_code(304)
+            } else {
+                Response::from_string(FEATURES_JSON)
+                    .with_header(Header::from_bytes("content-type", "application/json").unwrap())
+                    .with_header(Header::from_bytes("etag", ETAG).unwrap())
+            };
+
+            req.respond(res).unwrap();
+        }
+    });
+
+    format!("http://127.0.0.1:{port}")
+}
+
+fn assert_features(db: &Db) {
+    assert_eq!(db.feature_count(), 2);
+    assert_eq!(db.stable_feature_count(), 1);
+
+    let (let_else, version) = db.get_feature("let_else").unwrap();
+    assert_eq!(let_else.slug, "let_else");
+    assert_eq!(let_else.rfc_id, Some(3137));
+    assert_eq!(version.unwrap().number, "1.65");
+
+    assert_eq!(db.lookup("try", 20)[0].slug, "try_blocks");
+}
+
+#[test]
+fn fetch_parses_features() {
+    let url = serve();
+
+    let db = Db::fetch_with_agent(&ureq::agent(), &url).unwrap();
+    assert_features(&db);
+    assert_eq!(db.base_url(), url);
+}
+
+#[test]
+fn fetched_database_round_trips_through_cache() {
+    // cache directory is derived from these, so the user's real cache is left alone
+    let home = env::temp_dir().join(format!("alfred-caniuse-rs-it-{}", process::id()));
+    env::set_var("HOME", &home);
+    env::set_var("XDG_CACHE_HOME", home.join("cache"));
+
+    let url = serve();
+    let db = Db::fetch_with_agent(&ureq::agent(), &url).unwrap();
+    cache_put(&db);
+
+    let cached = cache_fetch();
+    let Cached::Fresh(cached) = cached else {
+        panic!("expected fresh cache, got {cached:?}");
+    };
+
+    assert_features(&cached);
+    assert_eq!(cached.base_url(), url);
+
+    // cached validators are sent so the unchanged remote answers 304
+    assert!(Db::fetch_if_modified(&url, &cached).unwrap().is_none());
+
+    let _ = fs::remove_dir_all(&home);
+}
+
+#[test]
+fn fetch_if_modified_not_modified() {
+    let url = serve();
+
+    let db = Db::fetch_with_agent(&ureq::agent(), &url).unwrap();
+    assert!(Db::fetch_if_modified(&url, &db).unwrap().is_none());
+
+    // without validators the full database is fetched again
+    let refetched = Db::fetch_if_modified(&url, &Db::default())
+        .unwrap()
+        .unwrap();
+    assert_features(&refetched);
+}